@@ -1,22 +1,63 @@
-use apollo_cw_asset::{Asset, AssetInfo, AssetInfoUnchecked, AssetList, AssetListUnchecked};
+use std::cmp::Reverse;
+
+use apollo_cw_asset::{
+    Asset, AssetInfo, AssetInfoKey, AssetInfoUnchecked, AssetList, AssetListUnchecked,
+    AssetUnchecked,
+};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, Event, MessageInfo,
-    Order, Response, StdResult, Uint128,
+    from_json, to_json_binary, Addr, Api, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Empty,
+    Env, Event, MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, SubMsg,
+    SubMsgResult, Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw20::Cw20ReceiveMsg;
+use cw_storage_plus::Bound;
 
 use crate::error::ContractError;
 use crate::helpers::{receive_asset, receive_assets};
-use crate::msg::{CallbackMsg, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
-use crate::operations::{SwapOperation, SwapOperationsList, SwapOperationsListUnchecked};
-use crate::state::{ADMIN, PATHS};
+use crate::msg::{
+    AllPathsResponse, BatchFailurePolicy, BatchSwapEntry, CallbackMsg, Cw20HookMsg, Deadline,
+    ExecuteMsg, InstantiateMsg, MigrateMsg, PathCandidate, PathEntry, PathFilter, PostSwapAction,
+    QueryMsg, Referral, RemovePathMsg, SetPathMsg, SimulateSwapResponse,
+};
+use crate::operations::{
+    SplitRoute, SplitRouteUnchecked, SwapOperation, SwapOperationsList, SwapOperationsListUnchecked,
+};
+use crate::pool::Pool;
+use crate::routing::{
+    find_best_route, optimize_split, simulate_hops, BestRouteResponse, PathHealth,
+    PathHealthResponse, RegisteredPool, SplitRouteResponse, MAX_REGISTERED_POOLS,
+};
+use crate::state::{
+    BatchSwapRefunds, Config, DcaSchedule, FeeConfig, LimitOrder, ADMIN, BATCH_SWAP_REFUNDS,
+    CONFIG, DCA_SCHEDULES, DCA_SCHEDULES_BY_OWNER, DISABLED_PATHS, FEE_CONFIG, FEE_EXEMPT_CALLERS,
+    LEGACY_PATHS, LIMIT_ORDERS, LIMIT_ORDERS_BY_OWNER, NEXT_DCA_SCHEDULE_ID, NEXT_LIMIT_ORDER_ID,
+    PATHS, PATHS_BY_ASK_ASSET, REFERRAL_VOLUMES, REGISTERED_POOLS, SWAPS_IN_PROGRESS,
+};
 
 const CONTRACT_NAME: &str = "crates.io:cw-dex-router";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Number of entries returned by the supported-asset, limit order and DCA
+/// schedule queries if no limit is given, and the most that can be requested.
+const DEFAULT_LIMIT: u32 = 30;
+const MAX_LIMIT: u32 = 100;
+/// Limits for `QueryMsg::PathHealth`, which are lower since every hop of every
+/// path is simulated.
+const DEFAULT_HEALTH_LIMIT: u32 = 10;
+const MAX_HEALTH_LIMIT: u32 = 30;
+/// Number of pairs `QueryMsg::AllPaths` reads at most per page, including the
+/// pairs the filter skips. A page that reaches it is returned with fewer pairs
+/// than requested, along with the pair to continue after.
+const MAX_SCANNED_PAIRS: usize = 300;
+/// Basis points in a whole, which is also the highest fee that can be set.
+const MAX_BPS: u16 = 10_000;
+/// Added to the id of a limit order to get the reply id of its fill. Lower
+/// reply ids are the indexes of the swaps of a `BatchSwap`.
+const LIMIT_ORDER_REPLY_ID_OFFSET: u64 = 1 << 63;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -43,7 +84,12 @@ pub fn execute(
             operations,
             offer_amount,
             minimum_receive,
+            max_slippage,
             to,
+            deadline,
+            post_swap_action,
+            recipients,
+            referral,
         } => {
             let operations = operations.check(deps.as_ref())?;
             execute_swap_operations(
@@ -54,24 +100,82 @@ pub fn execute(
                 operations,
                 offer_amount,
                 minimum_receive,
+                max_slippage,
+                to,
+                deadline,
+                post_swap_action,
+                recipients,
+                referral,
+            )
+        }
+        ExecuteMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer,
+            to,
+            deadline,
+            post_swap_action,
+        } => {
+            let operations = operations.check(deps.as_ref())?;
+            execute_swap_operations_exact_out(
+                deps,
+                env,
+                info,
+                operations,
+                ask_amount,
+                max_offer,
+                to,
+                deadline,
+                post_swap_action,
+            )
+        }
+        ExecuteMsg::ExecuteSplitRoute {
+            route,
+            offer_amount,
+            minimum_receive,
+            to,
+            deadline,
+            post_swap_action,
+        } => {
+            let route = route.check(deps.as_ref())?;
+            execute_split_route(
+                deps,
+                env,
+                info,
+                route,
+                offer_amount,
+                minimum_receive,
                 to,
+                deadline,
+                post_swap_action,
             )
         }
         ExecuteMsg::BasketLiquidate {
             offer_assets,
             receive_asset,
             minimum_receive,
+            max_slippage,
             to,
+            deadline,
+            post_swap_action,
+            recipients,
+            referral,
         } => {
             let api = deps.api;
             basket_liquidate(
                 deps,
                 env,
-                info,
+                info.clone(),
+                info.sender,
                 offer_assets.check(api)?,
                 receive_asset.check(api)?,
                 minimum_receive,
+                max_slippage,
                 to,
+                deadline,
+                post_swap_action,
+                recipients,
+                referral,
             )
         }
         ExecuteMsg::SetPath {
@@ -79,6 +183,8 @@ pub fn execute(
             ask_asset,
             path,
             bidirectional,
+            append,
+            reference_amount,
         } => {
             let path = path.check(deps.as_ref())?;
             let api = deps.api;
@@ -89,16 +195,276 @@ pub fn execute(
                 ask_asset.check(api)?,
                 path,
                 bidirectional,
+                append,
+                reference_amount,
+            )
+        }
+        ExecuteMsg::RemovePath {
+            offer_asset,
+            ask_asset,
+            path,
+            bidirectional,
+        } => {
+            let path = path.map(|x| x.check(deps.as_ref())).transpose()?;
+            let api = deps.api;
+            remove_path(
+                deps,
+                info,
+                offer_asset.check(api)?,
+                ask_asset.check(api)?,
+                path,
+                bidirectional,
+            )
+        }
+        ExecuteMsg::UpdatePaths { remove, set } => update_paths(deps, info, remove, set),
+        ExecuteMsg::SetPathDisabled {
+            offer_asset,
+            ask_asset,
+            disabled,
+        } => {
+            let api = deps.api;
+            set_path_disabled(
+                deps,
+                info,
+                offer_asset.check(api)?,
+                ask_asset.check(api)?,
+                disabled,
+            )
+        }
+        ExecuteMsg::UpdateConfig {
+            hub_assets,
+            max_referral_fee_bps,
+        } => {
+            let hub_assets = hub_assets
+                .map(|x| {
+                    x.into_iter()
+                        .map(|x| x.check(deps.api))
+                        .collect::<StdResult<Vec<_>>>()
+                })
+                .transpose()?;
+            update_config(deps, info, hub_assets, max_referral_fee_bps)
+        }
+        ExecuteMsg::UpdateFeeConfig {
+            fee_bps,
+            fee_collector,
+        } => update_fee_config(deps, info, fee_bps, fee_collector),
+        ExecuteMsg::UpdateFeeExemptCallers { add, remove } => {
+            update_fee_exempt_callers(deps, info, add, remove)
+        }
+        ExecuteMsg::SwapStoredPath {
+            offer_asset,
+            ask_asset,
+            minimum_receive,
+            to,
+            deadline,
+            post_swap_action,
+        } => {
+            let api = deps.api;
+            swap_stored_path(
+                deps,
+                env,
+                info.clone(),
+                info.sender,
+                offer_asset.check(api)?,
+                ask_asset.check(api)?,
+                minimum_receive,
+                to,
+                deadline,
+                post_swap_action,
+            )
+        }
+        ExecuteMsg::RegisterPools { pools } => register_pools(deps, info, pools),
+        ExecuteMsg::DeregisterPools { pools } => deregister_pools(deps, info, pools),
+        ExecuteMsg::SwapBestRoute {
+            offer_asset,
+            ask_asset,
+            max_hops,
+            minimum_receive,
+            to,
+            deadline,
+            post_swap_action,
+        } => {
+            let api = deps.api;
+            swap_best_route(
+                deps,
+                env,
+                info,
+                offer_asset.check(api)?,
+                ask_asset.check(api)?,
+                max_hops,
+                minimum_receive,
+                to,
+                deadline,
+                post_swap_action,
+            )
+        }
+        ExecuteMsg::BatchSwap {
+            swaps,
+            failure_policy,
+            deadline,
+        } => batch_swap(deps, env, info, swaps, failure_policy, deadline),
+        ExecuteMsg::PlaceLimitOrder {
+            offer_asset,
+            ask_asset,
+            minimum_receive,
+            tip,
+        } => {
+            let api = deps.api;
+            place_limit_order(
+                deps,
+                env,
+                info,
+                offer_asset.check(api)?,
+                ask_asset.check(api)?,
+                minimum_receive,
+                tip.unwrap_or_default(),
+            )
+        }
+        ExecuteMsg::CancelLimitOrder { order_id } => cancel_limit_order(deps, info, order_id),
+        ExecuteMsg::ExecuteLimitOrders { order_ids } => {
+            execute_limit_orders(deps, env, info, order_ids)
+        }
+        ExecuteMsg::CreateDcaSchedule {
+            offer,
+            ask,
+            amount_per_interval,
+            interval,
+            total_budget,
+            max_slippage,
+        } => {
+            let api = deps.api;
+            create_dca_schedule(
+                deps,
+                env,
+                info,
+                offer.check(api)?,
+                ask.check(api)?,
+                amount_per_interval,
+                interval,
+                total_budget,
+                max_slippage,
             )
         }
+        ExecuteMsg::TriggerDca { schedule_id } => trigger_dca(deps, env, schedule_id),
+        ExecuteMsg::SetDcaSchedulePaused {
+            schedule_id,
+            paused,
+        } => set_dca_schedule_paused(deps, info, schedule_id, paused),
+        ExecuteMsg::WithdrawDcaSchedule { schedule_id } => {
+            withdraw_dca_schedule(deps, info, schedule_id)
+        }
         ExecuteMsg::Callback(msg) => {
             if info.sender != env.contract.address {
                 return Err(ContractError::Unauthorized);
             }
             match msg {
-                CallbackMsg::ExecuteSwapOperation { operation, to } => {
-                    execute_swap_operation(deps, env, operation, to)
+                CallbackMsg::ExecuteSwapOperation {
+                    operation,
+                    offer_amount,
+                    next_operations,
+                    to,
+                    deadline,
+                } => execute_swap_operation(
+                    deps,
+                    env,
+                    operation,
+                    offer_amount,
+                    next_operations,
+                    to,
+                    deadline,
+                ),
+                CallbackMsg::ExecuteNextSwapOperation {
+                    operation,
+                    prev_balance,
+                    next_operations,
+                    to,
+                    deadline,
+                } => {
+                    let offer_amount = operation
+                        .offer_asset_info
+                        .query_balance(&deps.querier, &env.contract.address)?
+                        .checked_sub(prev_balance)?;
+                    execute_swap_operation(
+                        deps,
+                        env,
+                        operation,
+                        offer_amount,
+                        next_operations,
+                        to,
+                        deadline,
+                    )
+                }
+                CallbackMsg::ExecuteBatchSwapEntry {
+                    operations,
+                    offer_amount,
+                    minimum_receive,
+                    recipient,
+                    sender,
+                    deadline,
+                } => execute_batch_swap_entry(
+                    deps,
+                    env,
+                    operations,
+                    offer_amount,
+                    minimum_receive,
+                    recipient,
+                    sender,
+                    deadline,
+                ),
+                CallbackMsg::FillLimitOrder { order_id, keeper } => {
+                    fill_limit_order(deps, env, order_id, keeper)
+                }
+                CallbackMsg::ClearBatchSwapRefunds {} => {
+                    BATCH_SWAP_REFUNDS.remove(deps.storage);
+                    Ok(Response::default())
                 }
+                CallbackMsg::FinishSwap {} => {
+                    let swaps = SWAPS_IN_PROGRESS.load(deps.storage)?;
+                    SWAPS_IN_PROGRESS.save(deps.storage, &(swaps - 1))?;
+                    Ok(Response::default())
+                }
+                CallbackMsg::TransferReceived {
+                    asset_info,
+                    prev_balance,
+                    recipient,
+                } => transfer_received(deps, env, asset_info, prev_balance, recipient),
+                CallbackMsg::ExecutePostSwapAction {
+                    asset_info,
+                    prev_balance,
+                    contract_addr,
+                    msg,
+                } => execute_post_swap_action(
+                    deps,
+                    env,
+                    asset_info,
+                    prev_balance,
+                    contract_addr,
+                    msg,
+                ),
+                CallbackMsg::DistributeReceived {
+                    asset_info,
+                    prev_balance,
+                    recipients,
+                } => distribute_received(deps, env, asset_info, prev_balance, recipients),
+                CallbackMsg::RefundLeftovers {
+                    prev_balances,
+                    recipient,
+                } => refund_leftovers(deps, env, prev_balances, recipient),
+                CallbackMsg::CollectFee {
+                    asset_info,
+                    prev_balance,
+                    protocol_fee,
+                    referral,
+                    recipient,
+                } => collect_fee(
+                    deps,
+                    env,
+                    asset_info,
+                    prev_balance,
+                    protocol_fee,
+                    referral,
+                    recipient,
+                ),
                 CallbackMsg::AssertMinimumReceive {
                     asset_info,
                     prev_balance,
@@ -123,23 +489,88 @@ pub fn receive_cw20(
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+    // The received amount of the calling Cw20 is the offer asset
+    let offer_asset = Asset::new(AssetInfo::Cw20(info.sender.clone()), cw20_msg.amount);
 
     match from_json(&cw20_msg.msg)? {
         Cw20HookMsg::ExecuteSwapOperations {
             operations,
             minimum_receive,
+            max_slippage,
             to,
+            deadline,
+            post_swap_action,
+            recipients,
+            referral,
         } => {
             let operations = operations.check(deps.as_ref())?;
+            if operations.from() != offer_asset.info {
+                return Err(ContractError::InvalidSwapOperations {
+                    operations: operations.into(),
+                });
+            }
             execute_swap_operations(
                 deps,
                 env,
                 info,
                 sender,
                 operations,
-                None,
+                Some(offer_asset.amount),
+                minimum_receive,
+                max_slippage,
+                to,
+                deadline,
+                post_swap_action,
+                recipients,
+                referral,
+            )
+        }
+        Cw20HookMsg::BasketLiquidate {
+            receive_asset,
+            minimum_receive,
+            max_slippage,
+            to,
+            deadline,
+            post_swap_action,
+            recipients,
+            referral,
+        } => {
+            let api = deps.api;
+            basket_liquidate(
+                deps,
+                env,
+                info,
+                sender,
+                vec![offer_asset].into(),
+                receive_asset.check(api)?,
+                minimum_receive,
+                max_slippage,
+                to,
+                deadline,
+                post_swap_action,
+                recipients,
+                referral,
+            )
+        }
+        Cw20HookMsg::SwapStoredPath {
+            ask_asset,
+            minimum_receive,
+            to,
+            deadline,
+            post_swap_action,
+        } => {
+            let api = deps.api;
+            swap_stored_path(
+                deps,
+                env,
+                info,
+                sender,
+                offer_asset,
+                ask_asset.check(api)?,
                 minimum_receive,
                 to,
+                deadline,
+                post_swap_action,
             )
         }
     }
@@ -154,179 +585,2007 @@ pub fn execute_swap_operations(
     operations: SwapOperationsList,
     offer_amount: Option<Uint128>,
     minimum_receive: Option<Uint128>,
+    max_slippage: Option<Decimal>,
     to: Option<String>,
+    deadline: Option<Deadline>,
+    post_swap_action: Option<PostSwapAction>,
+    recipients: Option<Vec<(String, Decimal)>>,
+    referral: Option<Referral>,
 ) -> Result<Response, ContractError> {
+    assert_deadline(&env, deadline)?;
+
     //Validate input or use sender address if None
-    let recipient = to.map_or(Ok(sender), |x| deps.api.addr_validate(&x))?;
+    let recipients = recipients
+        .map(|x| validate_recipients(deps.api, x))
+        .transpose()?;
+    let recipient = swap_recipient(
+        deps.as_ref(),
+        &env,
+        &sender,
+        to,
+        &post_swap_action,
+        &recipients,
+    )?;
+    let (fee, referral, swap_to) = swap_fees(deps.as_ref(), &env, &sender, referral, &recipient)?;
 
     let target_asset_info = operations.to();
     let offer_asset_info = operations.from();
 
-    // 1. Validate sent asset. We only do this if the passed in optional
-    // `offer_amount` and in this case we do transfer from on it, given that
-    // the offer asset is a CW20. Otherwise we assume the caller already sent
-    // funds and in the first call of execute_swap_operation, we just use the
-    // whole contracts balance.
-    let mut msgs: Vec<CosmosMsg> = vec![];
-    if let Some(offer_amount) = offer_amount {
-        msgs.extend(receive_asset(
-            &info,
-            &env,
-            &Asset::new(offer_asset_info, offer_amount),
-        )?);
-    };
+    // 1. Validate sent asset. The contract's whole balance is never swapped.
+    let (offer_amount, mut msgs) = receive_offer(&info, &env, &offer_asset_info, offer_amount)?;
 
-    // 2. Loop and execute swap operations
-    let mut msgs: Vec<CosmosMsg> = operations.into_execute_msgs(&env, recipient.clone())?;
+    let minimum_receive = apply_max_slippage(minimum_receive, max_slippage, || {
+        let output = operations.simulate(deps.as_ref(), offer_amount)?;
+        Ok(deduct_fees(output, &fee, &referral))
+    })?;
+
+    // 2. Loop and execute swap operations, then refund any intermediate assets
+    // left in the contract to the sender and take the fee from the output
+    msgs.extend(operations.into_execute_msgs(&env, offer_amount, swap_to, deadline)?);
+    msgs.extend(refund_leftovers_msg(
+        deps.as_ref(),
+        &env,
+        [&operations],
+        &sender,
+    )?);
+    msgs.extend(collect_fee_msg(
+        deps.as_ref(),
+        &env,
+        &target_asset_info,
+        fee,
+        referral,
+        &recipient,
+    )?);
 
     // 3. Assert min receive
     if let Some(minimum_receive) = minimum_receive {
-        let recipient_balance =
-            target_asset_info.query_balance(&deps.querier, recipient.clone())?;
-        msgs.push(
-            CallbackMsg::AssertMinimumReceive {
-                asset_info: target_asset_info,
-                prev_balance: recipient_balance,
-                minimum_receive,
-                recipient,
-            }
-            .into_cosmos_msg(&env)?,
-        );
+        msgs.push(assert_minimum_receive_msg(
+            deps.as_ref(),
+            &env,
+            &target_asset_info,
+            minimum_receive,
+            &recipient,
+        )?);
     }
-    Ok(Response::new().add_messages(msgs))
+
+    // 4. Deliver the output with the post swap action, or split it between
+    // the recipients
+    msgs.extend(post_swap_action_msg(
+        deps.as_ref(),
+        &env,
+        &target_asset_info,
+        post_swap_action,
+    )?);
+    msgs.extend(distribute_received_msg(
+        deps.as_ref(),
+        &env,
+        &target_asset_info,
+        recipients,
+    )?);
+    guard_swap(deps.storage, &env, Response::new().add_messages(msgs))
 }
 
-pub fn execute_swap_operation(
+#[allow(clippy::too_many_arguments)]
+pub fn execute_swap_operations_exact_out(
     deps: DepsMut,
     env: Env,
-    operation: SwapOperation,
-    to: Addr,
+    info: MessageInfo,
+    operations: SwapOperationsList,
+    ask_amount: Uint128,
+    max_offer: Uint128,
+    to: Option<String>,
+    deadline: Option<Deadline>,
+    post_swap_action: Option<PostSwapAction>,
 ) -> Result<Response, ContractError> {
-    //We use all of the contracts balance.
-    let offer_amount = operation
-        .offer_asset_info
-        .query_balance(&deps.querier, env.contract.address.to_string())?;
-
-    if offer_amount.is_zero() {
-        return Ok(Response::default());
-    }
-
-    let event = Event::new("apollo/cw-dex-router/callback_execute_swap_operation")
-        .add_attribute("operation", format!("{:?}", operation))
-        .add_attribute("offer_amount", offer_amount)
-        .add_attribute("to", to.to_string());
+    assert_deadline(&env, deadline)?;
 
-    Ok(operation
-        .to_cosmos_response(deps.as_ref(), &env, offer_amount, None, to)?
-        .add_event(event))
-}
+    //Validate input or use sender address if None
+    let recipient = swap_recipient(
+        deps.as_ref(),
+        &env,
+        &info.sender,
+        to,
+        &post_swap_action,
+        &None,
+    )?;
+    let (fee, _, swap_to) = swap_fees(deps.as_ref(), &env, &info.sender, None, &recipient)?;
 
-pub fn assert_minimum_receive(
-    deps: DepsMut,
-    asset_info: AssetInfo,
-    prev_balance: Uint128,
-    minimum_receive: Uint128,
-    recipient: Addr,
-) -> Result<Response, ContractError> {
-    let recipient_balance = asset_info.query_balance(&deps.querier, recipient)?;
+    // 1. Find the offer amount needed to receive `ask_amount` after the fee
+    let offer_amount =
+        operations.simulate_reverse(deps.as_ref(), amount_before_fee(ask_amount, &fee)?)?;
+    if offer_amount > max_offer {
+        return Err(ContractError::MaxOfferExceeded {
+            offer_amount,
+            max_offer,
+        });
+    }
 
-    let received_amount = recipient_balance.checked_sub(prev_balance)?;
+    // 2. Assert max_offer is sent, and nothing else, or do TransferFrom on Cw20
+    let offer_asset_info = operations.from();
+    let (_, mut msgs) = receive_offer(&info, &env, &offer_asset_info, Some(max_offer))?;
 
-    if received_amount < minimum_receive {
-        return Err(ContractError::FailedMinimumReceive);
+    // 3. Swap only the needed amount, take the fee from the output and refund
+    // the rest of the offer asset to the sender
+    msgs.extend(operations.into_execute_msgs(&env, offer_amount, swap_to, deadline)?);
+    msgs.extend(collect_fee_msg(
+        deps.as_ref(),
+        &env,
+        &operations.to(),
+        fee,
+        None,
+        &recipient,
+    )?);
+    let refund_asset = Asset::new(offer_asset_info, max_offer - offer_amount);
+    if !refund_asset.amount.is_zero() {
+        msgs.push(refund_asset.transfer_msg(&info.sender)?);
     }
-    Ok(Response::default())
-}
 
-pub fn set_path(
-    deps: DepsMut,
-    info: MessageInfo,
-    offer_asset: AssetInfo,
-    ask_asset: AssetInfo,
-    path: SwapOperationsList,
-    bidirectional: bool,
-) -> Result<Response, ContractError> {
-    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    // 4. Assert that the full ask amount was received
+    msgs.push(assert_minimum_receive_msg(
+        deps.as_ref(),
+        &env,
+        &operations.to(),
+        ask_amount,
+        &recipient,
+    )?);
 
-    // Validate the path
-    if path.from() != offer_asset || path.to() != ask_asset {
-        return Err(ContractError::InvalidSwapOperations {
-            operations: path.into(),
-        });
-    }
+    // 5. Deliver the output with the post swap action
+    msgs.extend(post_swap_action_msg(
+        deps.as_ref(),
+        &env,
+        &operations.to(),
+        post_swap_action,
+    )?);
+
+    let event = Event::new("apollo/cw-dex-router/execute_swap_operations_exact_out")
+        .add_attribute("operations", format!("{:?}", operations))
+        .add_attribute("ask_amount", ask_amount)
+        .add_attribute("offer_amount", offer_amount)
+        .add_attribute("refund", refund_asset.to_string())
+        .add_attribute("recipient", recipient);
 
-    PATHS.save(
+    guard_swap(
         deps.storage,
-        ((&offer_asset).into(), (&ask_asset).into()),
-        &path,
-    )?;
-    // reverse path and store if `bidirectional` is true
-    if bidirectional {
-        PATHS.save(
-            deps.storage,
-            (ask_asset.into(), offer_asset.into()),
-            &path.reverse(),
-        )?;
-    }
-    Ok(Response::default())
+        &env,
+        Response::new().add_messages(msgs).add_event(event),
+    )
 }
 
-pub fn basket_liquidate(
+#[allow(clippy::too_many_arguments)]
+pub fn execute_split_route(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    offer_assets: AssetList,
-    receive_asset: AssetInfo,
+    route: SplitRoute,
+    offer_amount: Uint128,
     minimum_receive: Option<Uint128>,
     to: Option<String>,
+    deadline: Option<Deadline>,
+    post_swap_action: Option<PostSwapAction>,
 ) -> Result<Response, ContractError> {
-    //Validate input or use sender address if None
-    let recipient = to.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+    assert_deadline(&env, deadline)?;
 
-    // 1. Assert offer_assets are sent or do TransferFrom on Cw20s
+    //Validate input or use sender address if None
+    let recipient = swap_recipient(
+        deps.as_ref(),
+        &env,
+        &info.sender,
+        to,
+        &post_swap_action,
+        &None,
+    )?;
+    let (fee, _, swap_to) = swap_fees(deps.as_ref(), &env, &info.sender, None, &recipient)?;
+
+    // 1. Assert the offer asset is sent, and nothing else, or do TransferFrom
+    // on Cw20
+    let (_, mut msgs) = receive_offer(&info, &env, &route.from(), Some(offer_amount))?;
+
+    // 2. Execute the swap operations of each leg with its share of the offer,
+    // then take the fee from the combined output
+    msgs.extend(route.into_execute_msgs(&env, offer_amount, swap_to, deadline)?);
+    msgs.extend(collect_fee_msg(
+        deps.as_ref(),
+        &env,
+        &route.to(),
+        fee,
+        None,
+        &recipient,
+    )?);
+
+    // 3. Assert min receive on the combined output of all legs
+    if let Some(minimum_receive) = minimum_receive {
+        msgs.push(assert_minimum_receive_msg(
+            deps.as_ref(),
+            &env,
+            &route.to(),
+            minimum_receive,
+            &recipient,
+        )?);
+    }
+
+    // 4. Deliver the output with the post swap action
+    msgs.extend(post_swap_action_msg(
+        deps.as_ref(),
+        &env,
+        &route.to(),
+        post_swap_action,
+    )?);
+
+    let event = Event::new("apollo/cw-dex-router/execute_split_route")
+        .add_attribute("route", format!("{:?}", route))
+        .add_attribute("offer_amount", offer_amount)
+        .add_attribute("minimum_receive", minimum_receive.unwrap_or_default())
+        .add_attribute("recipient", recipient);
+
+    guard_swap(
+        deps.storage,
+        &env,
+        Response::new().add_messages(msgs).add_event(event),
+    )
+}
+
+/// Receives the offer asset of a swap and returns the amount to swap. A native
+/// offer asset must be the only coin sent, and match `offer_amount` if given.
+/// A Cw20 offer asset is received with TransferFrom on `offer_amount`, unless
+/// it was sent through `Receive` and is already held by the contract, and no
+/// coins may be sent with it.
+fn receive_offer(
+    info: &MessageInfo,
+    env: &Env,
+    offer_asset_info: &AssetInfo,
+    offer_amount: Option<Uint128>,
+) -> Result<(Uint128, Vec<CosmosMsg>), ContractError> {
+    match offer_asset_info {
+        AssetInfo::Native(denom) => {
+            let sent_amount = match info.funds.as_slice() {
+                [coin] if &coin.denom == denom && !coin.amount.is_zero() => coin.amount,
+                _ => return Err(ContractError::IncorrectNativeAmountSent),
+            };
+            if offer_amount.is_some_and(|amount| amount != sent_amount) {
+                return Err(ContractError::IncorrectNativeAmountSent);
+            }
+            Ok((sent_amount, vec![]))
+        }
+        AssetInfo::Cw20(_) => {
+            if !info.funds.is_empty() {
+                return Err(ContractError::IncorrectNativeAmountSent);
+            }
+            let offer_amount = offer_amount.ok_or(ContractError::MissingOfferAmount)?;
+            if is_received_cw20(info, offer_asset_info) {
+                return Ok((offer_amount, vec![]));
+            }
+            let msgs = receive_asset(
+                info,
+                env,
+                &Asset::new(offer_asset_info.clone(), offer_amount),
+            )?;
+            Ok((offer_amount, msgs))
+        }
+    }
+}
+
+/// Receives `asset` to be held by the contract. A native asset must be the only
+/// coin sent, and no coins may be sent with a Cw20.
+fn receive_escrow(
+    info: &MessageInfo,
+    env: &Env,
+    asset: &Asset,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let expected_funds = match asset.info {
+        AssetInfo::Native(_) => 1,
+        AssetInfo::Cw20(_) => 0,
+    };
+    if info.funds.len() != expected_funds {
+        return Err(ContractError::IncorrectNativeAmountSent);
+    }
+    Ok(receive_asset(info, env, asset)?)
+}
+
+/// Returns an error if a swap is being executed. Escrowed assets are held in
+/// the balances that the callbacks of the swap measure, so they may not be
+/// moved until it is done, e.g. by a contract the swap calls into.
+fn assert_no_swap_in_progress(storage: &dyn Storage) -> Result<(), ContractError> {
+    if SWAPS_IN_PROGRESS.may_load(storage)?.unwrap_or_default() > 0 {
+        return Err(ContractError::SwapInProgress);
+    }
+    Ok(())
+}
+
+/// Counts a swap as in progress until the messages of `response` are
+/// executed, after which the added `CallbackMsg::FinishSwap` ends it.
+fn guard_swap(
+    storage: &mut dyn Storage,
+    env: &Env,
+    response: Response,
+) -> Result<Response, ContractError> {
+    let swaps = SWAPS_IN_PROGRESS.may_load(storage)?.unwrap_or_default();
+    SWAPS_IN_PROGRESS.save(storage, &(swaps + 1))?;
+    Ok(response.add_message(CallbackMsg::FinishSwap {}.into_cosmos_msg(env)?))
+}
+
+/// Returns whether `asset_info` is the Cw20 that called the contract, which is
+/// the case when it was sent through `Receive` and is already held by the
+/// contract.
+fn is_received_cw20(info: &MessageInfo, asset_info: &AssetInfo) -> bool {
+    matches!(asset_info, AssetInfo::Cw20(addr) if *addr == info.sender)
+}
+
+/// Returns the address the output of a swap is sent to. This is `to` or
+/// `sender` if not given, or the contract itself if the output is delivered
+/// with a post swap action or split between several recipients.
+///
+/// The contract itself may not be given as `to`, as a recipient or as the
+/// post swap action contract. Its output would be left in the contract, and a
+/// post swap action on the contract would pass as one of its own callbacks.
+fn swap_recipient(
+    deps: Deps,
+    env: &Env,
+    sender: &Addr,
+    to: Option<String>,
+    post_swap_action: &Option<PostSwapAction>,
+    recipients: &Option<Vec<(Addr, Decimal)>>,
+) -> Result<Addr, ContractError> {
+    let kept_output = post_swap_action.is_some() || recipients.is_some();
+    if (to.is_some() && kept_output) || (post_swap_action.is_some() && recipients.is_some()) {
+        return Err(ContractError::MultipleSwapOutputs);
+    }
+
+    let to = to.map(|to| deps.api.addr_validate(&to)).transpose()?;
+    let post_swap_contract = post_swap_action
+        .as_ref()
+        .map(|action| deps.api.addr_validate(&action.contract_addr))
+        .transpose()?;
+    let mut outputs = to
+        .iter()
+        .chain(&post_swap_contract)
+        .chain(recipients.iter().flatten().map(|(recipient, _)| recipient));
+    if outputs.any(|output| *output == env.contract.address) {
+        return Err(ContractError::SwapOutputToRouter);
+    }
+
+    match to {
+        Some(to) => Ok(to),
+        None if kept_output => Ok(env.contract.address.clone()),
+        None => Ok(sender.clone()),
+    }
+}
+
+/// Validates the recipients of a split output. There must be at least one and
+/// all weights must be non-zero.
+fn validate_recipients(
+    api: &dyn Api,
+    recipients: Vec<(String, Decimal)>,
+) -> Result<Vec<(Addr, Decimal)>, ContractError> {
+    if recipients.is_empty() {
+        return Err(ContractError::InvalidRecipients {
+            reason: "no recipients given".to_string(),
+        });
+    }
+    recipients
+        .into_iter()
+        .map(|(address, weight)| {
+            if weight.is_zero() {
+                return Err(ContractError::InvalidRecipients {
+                    reason: format!("weight of {} is zero", address),
+                });
+            }
+            Ok((api.addr_validate(&address)?, weight))
+        })
+        .collect()
+}
+
+/// Returns a callback message splitting the amount of `asset_info` the
+/// contract receives between now and when the callback is executed between
+/// `recipients`, if given.
+fn distribute_received_msg(
+    deps: Deps,
+    env: &Env,
+    asset_info: &AssetInfo,
+    recipients: Option<Vec<(Addr, Decimal)>>,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    let Some(recipients) = recipients else {
+        return Ok(None);
+    };
+
+    let prev_balance = asset_info.query_balance(&deps.querier, &env.contract.address)?;
+    Ok(Some(
+        CallbackMsg::DistributeReceived {
+            asset_info: asset_info.clone(),
+            prev_balance,
+            recipients,
+        }
+        .into_cosmos_msg(env)?,
+    ))
+}
+
+/// Returns a callback message delivering the amount of `asset_info` the
+/// contract receives between now and when the callback is executed with
+/// `post_swap_action`, if given.
+fn post_swap_action_msg(
+    deps: Deps,
+    env: &Env,
+    asset_info: &AssetInfo,
+    post_swap_action: Option<PostSwapAction>,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    let Some(post_swap_action) = post_swap_action else {
+        return Ok(None);
+    };
+
+    let prev_balance = asset_info.query_balance(&deps.querier, &env.contract.address)?;
+    Ok(Some(
+        CallbackMsg::ExecutePostSwapAction {
+            asset_info: asset_info.clone(),
+            prev_balance,
+            contract_addr: deps.api.addr_validate(&post_swap_action.contract_addr)?,
+            msg: post_swap_action.msg,
+        }
+        .into_cosmos_msg(env)?,
+    ))
+}
+
+/// Returns the protocol fee config if a fee is charged on the swaps of
+/// `caller`.
+fn fee_for_caller(deps: Deps, caller: &Addr) -> StdResult<Option<FeeConfig>> {
+    if FEE_EXEMPT_CALLERS.has(deps.storage, caller) {
+        return Ok(None);
+    }
+    Ok(FEE_CONFIG
+        .may_load(deps.storage)?
+        .filter(|fee| fee.fee_bps > 0))
+}
+
+/// Returns the protocol fee charged on a swap of `sender`, the validated
+/// `referral` and the address the swap sends its output to. The output goes
+/// through the contract if any fee is taken from it, and straight to
+/// `recipient` otherwise.
+#[allow(clippy::type_complexity)]
+fn swap_fees(
+    deps: Deps,
+    env: &Env,
+    sender: &Addr,
+    referral: Option<Referral>,
+    recipient: &Addr,
+) -> Result<(Option<FeeConfig>, Option<(Addr, u16)>, Addr), ContractError> {
+    let fee = fee_for_caller(deps, sender)?;
+    let referral = validate_referral(deps, referral, &fee)?;
+    let swap_to = match (&fee, &referral) {
+        (None, None) => recipient.clone(),
+        _ => env.contract.address.clone(),
+    };
+    Ok((fee, referral, swap_to))
+}
+
+/// Validates `referral` against the maximum referral fee in the config. The
+/// referral fee and the protocol fee `fee` together may not exceed the whole
+/// output.
+fn validate_referral(
+    deps: Deps,
+    referral: Option<Referral>,
+    fee: &Option<FeeConfig>,
+) -> Result<Option<(Addr, u16)>, ContractError> {
+    let Some(referral) = referral else {
+        return Ok(None);
+    };
+
+    let max_fee_bps = CONFIG
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .max_referral_fee_bps
+        .min(MAX_BPS - fee.as_ref().map_or(0, |fee| fee.fee_bps));
+    if referral.fee_bps > max_fee_bps {
+        return Err(ContractError::InvalidFee {
+            fee_bps: referral.fee_bps,
+            max_fee_bps,
+        });
+    }
+    Ok(Some((
+        deps.api.addr_validate(&referral.address)?,
+        referral.fee_bps,
+    )))
+}
+
+/// Returns the fee of `fee_bps` charged on `amount`, rounded down.
+fn fee_amount(amount: Uint128, fee_bps: u16) -> Uint128 {
+    amount.multiply_ratio(fee_bps, MAX_BPS)
+}
+
+/// Returns the smallest amount that leaves at least `amount` after the
+/// protocol fee `fee`.
+fn amount_before_fee(amount: Uint128, fee: &Option<FeeConfig>) -> Result<Uint128, ContractError> {
+    let Some(fee) = fee else {
+        return Ok(amount);
+    };
+    if fee.fee_bps >= MAX_BPS {
+        return Err(ContractError::InvalidFee {
+            fee_bps: fee.fee_bps,
+            max_fee_bps: MAX_BPS - 1,
+        });
+    }
+    // Rounding down here is enough since the fee itself is rounded down
+    Ok(amount.multiply_ratio(MAX_BPS, MAX_BPS - fee.fee_bps))
+}
+
+/// Returns what is left of `amount` after the protocol fee and the referral
+/// fee.
+fn deduct_fees(
+    amount: Uint128,
+    fee: &Option<FeeConfig>,
+    referral: &Option<(Addr, u16)>,
+) -> Uint128 {
+    let fee_bps = [
+        fee.as_ref().map(|fee| fee.fee_bps),
+        referral.as_ref().map(|(_, fee_bps)| *fee_bps),
+    ];
+    fee_bps
+        .into_iter()
+        .flatten()
+        .fold(amount, |rest, fee_bps| rest - fee_amount(amount, fee_bps))
+}
+
+/// Returns a callback message taking `fee` and the `referral` fee from the
+/// amount of `asset_info` the contract receives between now and when the
+/// callback is executed, and sending the rest to `recipient`, if any fee is
+/// charged.
+fn collect_fee_msg(
+    deps: Deps,
+    env: &Env,
+    asset_info: &AssetInfo,
+    fee: Option<FeeConfig>,
+    referral: Option<(Addr, u16)>,
+    recipient: &Addr,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    if fee.is_none() && referral.is_none() {
+        return Ok(None);
+    }
+
+    let prev_balance = asset_info.query_balance(&deps.querier, &env.contract.address)?;
+    Ok(Some(
+        CallbackMsg::CollectFee {
+            asset_info: asset_info.clone(),
+            prev_balance,
+            protocol_fee: fee.map(|fee| (fee.fee_collector, fee.fee_bps)),
+            referral,
+            recipient: recipient.clone(),
+        }
+        .into_cosmos_msg(env)?,
+    ))
+}
+
+/// Returns an error if `deadline` is given and has passed.
+fn assert_deadline(env: &Env, deadline: Option<Deadline>) -> Result<(), ContractError> {
+    deadline.map_or(Ok(()), |deadline| deadline.assert_not_passed(&env.block))
+}
+
+/// Returns the minimum amount to receive from `minimum_receive` and
+/// `max_slippage`. The slippage is applied to the output returned by
+/// `simulate`, which is only called if `max_slippage` is given. If both are
+/// given the higher minimum is used.
+fn apply_max_slippage(
+    minimum_receive: Option<Uint128>,
+    max_slippage: Option<Decimal>,
+    simulate: impl FnOnce() -> Result<Uint128, ContractError>,
+) -> Result<Option<Uint128>, ContractError> {
+    let Some(max_slippage) = max_slippage else {
+        return Ok(minimum_receive);
+    };
+    if max_slippage > Decimal::one() {
+        return Err(ContractError::InvalidMaxSlippage { max_slippage });
+    }
+
+    let minimum_from_slippage = simulate()? * (Decimal::one() - max_slippage);
+    Ok(Some(
+        minimum_receive.map_or(minimum_from_slippage, |minimum| {
+            minimum.max(minimum_from_slippage)
+        }),
+    ))
+}
+
+/// Returns a callback message refunding to `recipient` any increase in the
+/// contract's balances of the intermediate assets of `paths` between now and
+/// when the callback is executed. The offer and ask assets of the paths are
+/// never refunded, even if a path swaps through them. Returns `None` if there
+/// are no other intermediate assets.
+fn refund_leftovers_msg<'a>(
+    deps: Deps,
+    env: &Env,
+    paths: impl IntoIterator<Item = &'a SwapOperationsList>,
+    recipient: &Addr,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    let paths = paths.into_iter().collect::<Vec<_>>();
+    let excluded = paths
+        .iter()
+        .flat_map(|path| [path.from(), path.to()])
+        .collect::<Vec<_>>();
+
+    let mut prev_balances: Vec<Asset> = vec![];
+    for path in paths {
+        let operations = path.operations();
+        for operation in &operations[..operations.len() - 1] {
+            let asset_info = &operation.ask_asset_info;
+            if excluded.contains(asset_info) || prev_balances.iter().any(|x| &x.info == asset_info)
+            {
+                continue;
+            }
+            let balance = asset_info.query_balance(&deps.querier, &env.contract.address)?;
+            prev_balances.push(Asset::new(asset_info.clone(), balance));
+        }
+    }
+
+    if prev_balances.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        CallbackMsg::RefundLeftovers {
+            prev_balances: prev_balances.into(),
+            recipient: recipient.clone(),
+        }
+        .into_cosmos_msg(env)?,
+    ))
+}
+
+/// Returns a callback message asserting that `recipient` has received at least
+/// `minimum_receive` of `asset_info` since this was called.
+fn assert_minimum_receive_msg(
+    deps: Deps,
+    env: &Env,
+    asset_info: &AssetInfo,
+    minimum_receive: Uint128,
+    recipient: &Addr,
+) -> Result<CosmosMsg, ContractError> {
+    let recipient_balance = asset_info.query_balance(&deps.querier, recipient)?;
+    CallbackMsg::AssertMinimumReceive {
+        asset_info: asset_info.clone(),
+        prev_balance: recipient_balance,
+        minimum_receive,
+        recipient: recipient.clone(),
+    }
+    .into_cosmos_msg(env)
+}
+
+pub fn execute_swap_operation(
+    deps: DepsMut,
+    env: Env,
+    operation: SwapOperation,
+    offer_amount: Uint128,
+    next_operations: Vec<SwapOperation>,
+    to: Addr,
+    deadline: Option<Deadline>,
+) -> Result<Response, ContractError> {
+    assert_deadline(&env, deadline)?;
+
+    if offer_amount.is_zero() {
+        return Ok(Response::default());
+    }
+
+    let minimum_receive = operation.minimum_receive_for(offer_amount)?;
+
+    // The output is kept by the contract and only the amount received is
+    // forwarded, to the next operation or to the recipient after the last one.
+    // This way other balances of the ask asset in the contract are left
+    // untouched and the amount is not taken from a simulation.
+    let prev_balance = operation
+        .ask_asset_info
+        .query_balance(&deps.querier, &env.contract.address)?;
+    let next_msg = match next_operations.split_first() {
+        Some((next_operation, next_operations)) => CallbackMsg::ExecuteNextSwapOperation {
+            operation: next_operation.clone(),
+            prev_balance,
+            next_operations: next_operations.to_vec(),
+            to: to.clone(),
+            deadline,
+        },
+        None => CallbackMsg::TransferReceived {
+            asset_info: operation.ask_asset_info.clone(),
+            prev_balance,
+            recipient: to.clone(),
+        },
+    }
+    .into_cosmos_msg(&env)?;
+
+    let event = Event::new("apollo/cw-dex-router/callback_execute_swap_operation")
+        .add_attribute("operation", format!("{:?}", operation))
+        .add_attribute("offer_amount", offer_amount)
+        .add_attribute("to", to.to_string());
+
+    Ok(operation
+        .to_cosmos_response(deps.as_ref(), &env, offer_amount, minimum_receive)?
+        .add_message(next_msg)
+        .add_event(event))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_batch_swap_entry(
+    deps: DepsMut,
+    env: Env,
+    operations: SwapOperationsList,
+    offer_amount: Uint128,
+    minimum_receive: Option<Uint128>,
+    recipient: Addr,
+    sender: Addr,
+    deadline: Option<Deadline>,
+) -> Result<Response, ContractError> {
+    let (fee, _, swap_to) = swap_fees(deps.as_ref(), &env, &sender, None, &recipient)?;
+
+    // Balances are snapshotted here rather than when the batch is received, so
+    // that only what this swap receives is counted
+    let mut msgs = operations.into_execute_msgs(&env, offer_amount, swap_to, deadline)?;
+    msgs.extend(refund_leftovers_msg(
+        deps.as_ref(),
+        &env,
+        [&operations],
+        &sender,
+    )?);
+    msgs.extend(collect_fee_msg(
+        deps.as_ref(),
+        &env,
+        &operations.to(),
+        fee,
+        None,
+        &recipient,
+    )?);
+    if let Some(minimum_receive) = minimum_receive {
+        msgs.push(assert_minimum_receive_msg(
+            deps.as_ref(),
+            &env,
+            &operations.to(),
+            minimum_receive,
+            &recipient,
+        )?);
+    }
+
+    Ok(Response::new().add_messages(msgs))
+}
+
+pub fn transfer_received(
+    deps: DepsMut,
+    env: Env,
+    asset_info: AssetInfo,
+    prev_balance: Uint128,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let received = asset_info
+        .query_balance(&deps.querier, &env.contract.address)?
+        .checked_sub(prev_balance)?;
+    let received_asset = Asset::new(asset_info, received);
+
+    let event = Event::new("apollo/cw-dex-router/callback_transfer_received")
+        .add_attribute("asset", received_asset.to_string())
+        .add_attribute("recipient", recipient.to_string());
+
+    // The output is kept if the contract itself is the recipient, e.g. to be
+    // delivered with a post swap action
+    let mut response = Response::new().add_event(event);
+    if !received_asset.amount.is_zero() && recipient != env.contract.address {
+        response = response.add_message(received_asset.transfer_msg(&recipient)?);
+    }
+    Ok(response)
+}
+
+pub fn execute_post_swap_action(
+    deps: DepsMut,
+    env: Env,
+    asset_info: AssetInfo,
+    prev_balance: Uint128,
+    contract_addr: Addr,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    let received = asset_info
+        .query_balance(&deps.querier, &env.contract.address)?
+        .checked_sub(prev_balance)?;
+
+    // Nothing is left to deliver, e.g. if the fees took the whole output
+    if received.is_zero() {
+        return Ok(Response::default());
+    }
+    let received_asset = Asset::new(asset_info, received);
+
+    let action_msg: CosmosMsg = match &received_asset.info {
+        AssetInfo::Native(denom) => WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg,
+            funds: vec![Coin::new(received.u128(), denom)],
+        }
+        .into(),
+        AssetInfo::Cw20(_) => received_asset.send_msg(&contract_addr, msg)?,
+    };
+
+    let event = Event::new("apollo/cw-dex-router/callback_execute_post_swap_action")
+        .add_attribute("asset", received_asset.to_string())
+        .add_attribute("contract_addr", contract_addr.to_string());
+
+    Ok(Response::new().add_message(action_msg).add_event(event))
+}
+
+pub fn distribute_received(
+    deps: DepsMut,
+    env: Env,
+    asset_info: AssetInfo,
+    prev_balance: Uint128,
+    recipients: Vec<(Addr, Decimal)>,
+) -> Result<Response, ContractError> {
+    let received = asset_info
+        .query_balance(&deps.querier, &env.contract.address)?
+        .checked_sub(prev_balance)?;
+    let total_weight = recipients
+        .iter()
+        .try_fold(Uint128::zero(), |total, (_, weight)| {
+            total.checked_add(weight.atomics())
+        })?;
+
+    // Every recipient but the first gets its share rounded down, and the first
+    // gets the rest
+    let mut shares: Vec<(Addr, Uint128)> = recipients
+        .iter()
+        .skip(1)
+        .map(|(recipient, weight)| {
+            let share = received.multiply_ratio(weight.atomics(), total_weight);
+            (recipient.clone(), share)
+        })
+        .collect();
+    let distributed = shares.iter().map(|(_, share)| *share).sum::<Uint128>();
+    shares.insert(0, (recipients[0].0.clone(), received - distributed));
+
+    let mut event = Event::new("apollo/cw-dex-router/callback_distribute_received").add_attribute(
+        "asset",
+        Asset::new(asset_info.clone(), received).to_string(),
+    );
+    let mut msgs = vec![];
+    for (recipient, share) in shares {
+        event = event.add_attribute(recipient.to_string(), share);
+        if !share.is_zero() {
+            msgs.push(Asset::new(asset_info.clone(), share).transfer_msg(&recipient)?);
+        }
+    }
+
+    Ok(Response::new().add_messages(msgs).add_event(event))
+}
+
+pub fn refund_leftovers(
+    deps: DepsMut,
+    env: Env,
+    prev_balances: AssetList,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let mut refunds = vec![];
+    for prev_balance in prev_balances.into_iter() {
+        let balance = prev_balance
+            .info
+            .query_balance(&deps.querier, &env.contract.address)?;
+        if balance > prev_balance.amount {
+            refunds.push(Asset::new(
+                prev_balance.info.clone(),
+                balance - prev_balance.amount,
+            ));
+        }
+    }
+
+    if refunds.is_empty() {
+        return Ok(Response::default());
+    }
+    let refunds = AssetList::from(refunds);
+
+    let event = Event::new("apollo/cw-dex-router/refund_leftovers")
+        .add_attribute("assets", refunds.to_string())
+        .add_attribute("recipient", recipient.to_string());
+
+    Ok(Response::new()
+        .add_messages(refunds.transfer_msgs(&recipient)?)
+        .add_event(event))
+}
+
+pub fn collect_fee(
+    deps: DepsMut,
+    env: Env,
+    asset_info: AssetInfo,
+    prev_balance: Uint128,
+    protocol_fee: Option<(Addr, u16)>,
+    referral: Option<(Addr, u16)>,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let received = asset_info
+        .query_balance(&deps.querier, &env.contract.address)?
+        .checked_sub(prev_balance)?;
+
+    let mut event = Event::new("apollo/cw-dex-router/callback_collect_fee").add_attribute(
+        "received",
+        Asset::new(asset_info.clone(), received).to_string(),
+    );
+    let mut msgs = vec![];
+    let mut net = received;
+    if let Some((fee_collector, fee_bps)) = protocol_fee {
+        let fee = Asset::new(asset_info.clone(), fee_amount(received, fee_bps));
+        net = net.checked_sub(fee.amount)?;
+        event = event
+            .add_attribute("fee", fee.to_string())
+            .add_attribute("fee_collector", fee_collector.to_string());
+        if !fee.amount.is_zero() {
+            msgs.push(fee.transfer_msg(&fee_collector)?);
+        }
+    }
+    if let Some((referrer, fee_bps)) = referral {
+        let referral_fee = Asset::new(asset_info.clone(), fee_amount(received, fee_bps));
+        net = net.checked_sub(referral_fee.amount)?;
+        REFERRAL_VOLUMES.update(deps.storage, (&referrer, (&asset_info).into()), |volume| {
+            Ok::<_, ContractError>(volume.unwrap_or_default().checked_add(received)?)
+        })?;
+        event = event
+            .add_attribute("referral_fee", referral_fee.to_string())
+            .add_attribute("referrer", referrer.to_string());
+        if !referral_fee.amount.is_zero() {
+            msgs.push(referral_fee.transfer_msg(&referrer)?);
+        }
+    }
+
+    event = event
+        .add_attribute("net", Asset::new(asset_info.clone(), net).to_string())
+        .add_attribute("recipient", recipient.to_string());
+
+    // The rest is kept if the contract itself is the recipient, e.g. to be
+    // delivered with a post swap action
+    if !net.is_zero() && recipient != env.contract.address {
+        msgs.push(Asset::new(asset_info, net).transfer_msg(&recipient)?);
+    }
+    Ok(Response::new().add_messages(msgs).add_event(event))
+}
+
+pub fn assert_minimum_receive(
+    deps: DepsMut,
+    asset_info: AssetInfo,
+    prev_balance: Uint128,
+    minimum_receive: Uint128,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let recipient_balance = asset_info.query_balance(&deps.querier, recipient)?;
+
+    let received_amount = recipient_balance.checked_sub(prev_balance)?;
+
+    if received_amount < minimum_receive {
+        return Err(ContractError::FailedMinimumReceive);
+    }
+    Ok(Response::default())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn set_path(
+    deps: DepsMut,
+    info: MessageInfo,
+    offer_asset: AssetInfo,
+    ask_asset: AssetInfo,
+    path: SwapOperationsList,
+    bidirectional: bool,
+    append: bool,
+    reference_amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    add_path(
+        deps,
+        &offer_asset,
+        &ask_asset,
+        path,
+        bidirectional,
+        append,
+        reference_amount,
+    )?;
+
+    Ok(Response::default())
+}
+
+pub fn remove_path(
+    deps: DepsMut,
+    info: MessageInfo,
+    offer_asset: AssetInfo,
+    ask_asset: AssetInfo,
+    path: Option<SwapOperationsList>,
+    bidirectional: bool,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    delete_path(deps.storage, &offer_asset, &ask_asset, path, bidirectional)?;
+
+    Ok(Response::default())
+}
+
+pub fn update_paths(
+    mut deps: DepsMut,
+    info: MessageInfo,
+    remove: Vec<RemovePathMsg>,
+    set: Vec<SetPathMsg>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    for msg in remove {
+        let path = msg.path.map(|x| x.check(deps.as_ref())).transpose()?;
+        delete_path(
+            deps.storage,
+            &msg.offer_asset.check(deps.api)?,
+            &msg.ask_asset.check(deps.api)?,
+            path,
+            msg.bidirectional,
+        )?;
+    }
+    for msg in set {
+        let path = msg.path.check(deps.as_ref())?;
+        let offer_asset = msg.offer_asset.check(deps.api)?;
+        let ask_asset = msg.ask_asset.check(deps.api)?;
+        add_path(
+            deps.branch(),
+            &offer_asset,
+            &ask_asset,
+            path,
+            msg.bidirectional,
+            msg.append,
+            msg.reference_amount,
+        )?;
+    }
+
+    Ok(Response::default())
+}
+
+pub fn set_path_disabled(
+    deps: DepsMut,
+    info: MessageInfo,
+    offer_asset: AssetInfo,
+    ask_asset: AssetInfo,
+    disabled: bool,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    // Only pairs with stored paths can be disabled
+    let key = ((&offer_asset).into(), (&ask_asset).into());
+    if !PATHS.has(deps.storage, key.clone()) {
+        return Err(ContractError::NoPathFound {
+            offer: offer_asset.to_string(),
+            ask: ask_asset.to_string(),
+        });
+    }
+
+    if disabled {
+        DISABLED_PATHS.save(deps.storage, key, &Empty {})?;
+    } else {
+        DISABLED_PATHS.remove(deps.storage, key);
+    }
+
+    Ok(Response::default())
+}
+
+pub fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    hub_assets: Option<Vec<AssetInfo>>,
+    max_referral_fee_bps: Option<u16>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut config = CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    if let Some(hub_assets) = hub_assets {
+        config.hub_assets = hub_assets;
+    }
+    if let Some(max_referral_fee_bps) = max_referral_fee_bps {
+        if max_referral_fee_bps > MAX_BPS {
+            return Err(ContractError::InvalidFee {
+                fee_bps: max_referral_fee_bps,
+                max_fee_bps: MAX_BPS,
+            });
+        }
+        config.max_referral_fee_bps = max_referral_fee_bps;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::default())
+}
+
+pub fn update_fee_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee_bps: u16,
+    fee_collector: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if fee_bps > MAX_BPS {
+        return Err(ContractError::InvalidFee {
+            fee_bps,
+            max_fee_bps: MAX_BPS,
+        });
+    }
+    FEE_CONFIG.save(
+        deps.storage,
+        &FeeConfig {
+            fee_bps,
+            fee_collector: deps.api.addr_validate(&fee_collector)?,
+        },
+    )?;
+
+    Ok(Response::default())
+}
+
+pub fn update_fee_exempt_callers(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    for caller in remove {
+        FEE_EXEMPT_CALLERS.remove(deps.storage, &deps.api.addr_validate(&caller)?);
+    }
+    for caller in add {
+        FEE_EXEMPT_CALLERS.save(deps.storage, &deps.api.addr_validate(&caller)?, &Empty {})?;
+    }
+
+    Ok(Response::default())
+}
+
+/// Validates that `path` swaps from `offer_asset` to `ask_asset` and stores it,
+/// along with the reversed path if `bidirectional` is true. The reversed path
+/// is validated against the pools the same way as `path`. If
+/// `reference_amount` is given, both paths must simulate to a non-zero output
+/// for it before either is stored.
+fn add_path(
+    deps: DepsMut,
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+    path: SwapOperationsList,
+    bidirectional: bool,
+    append: bool,
+    reference_amount: Option<Uint128>,
+) -> Result<(), ContractError> {
+    // Validate the path
+    if &path.from() != offer_asset || &path.to() != ask_asset {
+        return Err(ContractError::InvalidSwapOperations {
+            operations: path.into(),
+        });
+    }
+    if let Some(amount) = reference_amount {
+        assert_path_output(deps.as_ref(), &path, amount)?;
+    }
+
+    // reverse path, validate and store if `bidirectional` is true
+    if bidirectional {
+        let reversed = SwapOperationsListUnchecked::from(path.reverse()).check(deps.as_ref())?;
+        if let Some(amount) = reference_amount {
+            assert_path_output(deps.as_ref(), &reversed, amount)?;
+        }
+        store_path(deps.storage, reversed, append)?;
+    }
+    store_path(deps.storage, path, append)?;
+
+    Ok(())
+}
+
+/// Asserts that `path` simulates to a non-zero output for `offer_amount`.
+fn assert_path_output(
+    deps: Deps,
+    path: &SwapOperationsList,
+    offer_amount: Uint128,
+) -> Result<(), ContractError> {
+    if path.simulate(deps, offer_amount)?.is_zero() {
+        return Err(ContractError::ZeroPathOutput {
+            offer: path.from().to_string(),
+            ask: path.to().to_string(),
+            offer_amount,
+        });
+    }
+    Ok(())
+}
+
+/// Removes `path`, or all candidates if `None`, from the paths stored for the
+/// pair, along with the reversed path if `bidirectional` is true.
+fn delete_path(
+    storage: &mut dyn Storage,
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+    path: Option<SwapOperationsList>,
+    bidirectional: bool,
+) -> Result<(), ContractError> {
+    if bidirectional {
+        let reversed = path.as_ref().map(|x| x.reverse());
+        delete_candidates(storage, ask_asset, offer_asset, reversed)?;
+    }
+    delete_candidates(storage, offer_asset, ask_asset, path)
+}
+
+fn delete_candidates(
+    storage: &mut dyn Storage,
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+    path: Option<SwapOperationsList>,
+) -> Result<(), ContractError> {
+    let not_found = || ContractError::NoPathFound {
+        offer: offer_asset.to_string(),
+        ask: ask_asset.to_string(),
+    };
+
+    let key = (offer_asset.into(), ask_asset.into());
+    let mut candidates = PATHS
+        .may_load(storage, key.clone())?
+        .ok_or_else(not_found)?;
+    match path {
+        Some(path) => {
+            let len = candidates.len();
+            candidates.retain(|x| x != &path);
+            if candidates.len() == len {
+                return Err(not_found());
+            }
+        }
+        None => candidates.clear(),
+    }
+
+    // Nothing is left to disable once all candidates are removed
+    if candidates.is_empty() {
+        remove_candidates(storage, offer_asset, ask_asset);
+        DISABLED_PATHS.remove(storage, key);
+    } else {
+        save_candidates(storage, offer_asset, ask_asset, &candidates)?;
+    }
+
+    Ok(())
+}
+
+/// Stores `path` as a candidate for the pair it swaps between. Replaces any
+/// existing candidates unless `append` is true.
+fn store_path(storage: &mut dyn Storage, path: SwapOperationsList, append: bool) -> StdResult<()> {
+    let (offer_asset, ask_asset) = (path.from(), path.to());
+    let candidates = if append {
+        let mut candidates = PATHS
+            .may_load(storage, ((&offer_asset).into(), (&ask_asset).into()))?
+            .unwrap_or_default();
+        if !candidates.contains(&path) {
+            candidates.push(path);
+        }
+        candidates
+    } else {
+        vec![path]
+    };
+    save_candidates(storage, &offer_asset, &ask_asset, &candidates)
+}
+
+/// Saves the candidate paths for a pair and adds the pair to the ask asset
+/// index.
+fn save_candidates(
+    storage: &mut dyn Storage,
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+    candidates: &Vec<SwapOperationsList>,
+) -> StdResult<()> {
+    PATHS.save(storage, (offer_asset.into(), ask_asset.into()), candidates)?;
+    PATHS_BY_ASK_ASSET.save(storage, (ask_asset.into(), offer_asset.into()), &Empty {})
+}
+
+/// Removes the candidate paths for a pair and the pair from the ask asset
+/// index.
+fn remove_candidates(storage: &mut dyn Storage, offer_asset: &AssetInfo, ask_asset: &AssetInfo) {
+    PATHS.remove(storage, (offer_asset.into(), ask_asset.into()));
+    PATHS_BY_ASK_ASSET.remove(storage, (ask_asset.into(), offer_asset.into()));
+}
+
+pub fn register_pools(
+    deps: DepsMut,
+    info: MessageInfo,
+    pools: Vec<Pool>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut registered = REGISTERED_POOLS.may_load(deps.storage)?.unwrap_or_default();
+    for pool in pools {
+        if registered.iter().any(|x| x.pool == pool) {
+            continue;
+        }
+        // Querying the pool assets also validates that the pool exists
+        registered.push(RegisteredPool::new(deps.as_ref(), pool)?);
+    }
+    if registered.len() > MAX_REGISTERED_POOLS {
+        return Err(ContractError::TooManyRegisteredPools {
+            max_pools: MAX_REGISTERED_POOLS,
+        });
+    }
+    REGISTERED_POOLS.save(deps.storage, &registered)?;
+
+    Ok(Response::default())
+}
+
+pub fn deregister_pools(
+    deps: DepsMut,
+    info: MessageInfo,
+    pools: Vec<Pool>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut registered = REGISTERED_POOLS.may_load(deps.storage)?.unwrap_or_default();
+    registered.retain(|x| !pools.contains(&x.pool));
+    REGISTERED_POOLS.save(deps.storage, &registered)?;
+
+    Ok(Response::default())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap_stored_path(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    sender: Addr,
+    offer_asset: Asset,
+    ask_asset: AssetInfo,
+    minimum_receive: Option<Uint128>,
+    to: Option<String>,
+    deadline: Option<Deadline>,
+    post_swap_action: Option<PostSwapAction>,
+) -> Result<Response, ContractError> {
+    let path = best_path_for_pair(deps.as_ref(), &offer_asset, &ask_asset)?;
+
+    execute_swap_operations(
+        deps,
+        env,
+        info,
+        sender,
+        path,
+        Some(offer_asset.amount),
+        minimum_receive,
+        None,
+        to,
+        deadline,
+        post_swap_action,
+        None,
+        None,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap_best_route(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    offer_asset: Asset,
+    ask_asset: AssetInfo,
+    max_hops: Option<u32>,
+    minimum_receive: Option<Uint128>,
+    to: Option<String>,
+    deadline: Option<Deadline>,
+    post_swap_action: Option<PostSwapAction>,
+) -> Result<Response, ContractError> {
+    assert_deadline(&env, deadline)?;
+
+    let route = find_best_route(
+        deps.as_ref(),
+        &offer_asset.info,
+        offer_asset.amount,
+        &ask_asset,
+        max_hops,
+    )?;
+
+    let event = Event::new("apollo/cw-dex-router/swap_best_route")
+        .add_attribute("offer_asset", offer_asset.to_string())
+        .add_attribute("ask_asset", ask_asset.to_string())
+        .add_attribute("operations", format!("{:?}", route.operations))
+        .add_attribute("simulated_return_amount", route.return_amount);
+
+    Ok(execute_swap_operations(
+        deps,
+        env,
+        info.clone(),
+        info.sender,
+        route.operations,
+        Some(offer_asset.amount),
+        minimum_receive,
+        None,
+        to,
+        deadline,
+        post_swap_action,
+        None,
+        None,
+    )?
+    .add_event(event))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn basket_liquidate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    sender: Addr,
+    offer_assets: AssetList,
+    receive_asset: AssetInfo,
+    minimum_receive: Option<Uint128>,
+    max_slippage: Option<Decimal>,
+    to: Option<String>,
+    deadline: Option<Deadline>,
+    post_swap_action: Option<PostSwapAction>,
+    recipients: Option<Vec<(String, Decimal)>>,
+    referral: Option<Referral>,
+) -> Result<Response, ContractError> {
+    assert_deadline(&env, deadline)?;
+
+    //Validate input or use sender address if None
+    let recipients = recipients
+        .map(|x| validate_recipients(deps.api, x))
+        .transpose()?;
+    let recipient = swap_recipient(
+        deps.as_ref(),
+        &env,
+        &sender,
+        to,
+        &post_swap_action,
+        &recipients,
+    )?;
+    let (fee, referral, swap_to) = swap_fees(deps.as_ref(), &env, &sender, referral, &recipient)?;
+
+    // 1. Assert offer_assets are sent or do TransferFrom on Cw20s. Cw20s sent
+    // through `Receive` are already held by the contract.
+    let unreceived_assets: Vec<Asset> = offer_assets
+        .into_iter()
+        .filter(|asset| !is_received_cw20(&info, &asset.info))
+        .cloned()
+        .collect();
+    let receive_msgs = receive_assets(&info, &env, &unreceived_assets.into())?;
+
+    // 2. Loop over offer assets and for each:
+    // Fetch the best path and call ExecuteMsg::ExecuteSwapOperations
+    let paths = offer_assets
+        .into_iter()
+        .map(|asset| {
+            let path = best_path_for_pair(deps.as_ref(), asset, &receive_asset)?;
+            Ok((asset.amount, path))
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+    let mut msgs = paths.iter().try_fold(vec![], |mut msgs, (amount, path)| {
+        msgs.extend(path.into_execute_msgs(&env, *amount, swap_to.clone(), deadline)?);
+        Ok::<Vec<_>, ContractError>(msgs)
+    })?;
+    msgs.extend(refund_leftovers_msg(
+        deps.as_ref(),
+        &env,
+        paths.iter().map(|(_, path)| path),
+        &sender,
+    )?);
+    msgs.extend(collect_fee_msg(
+        deps.as_ref(),
+        &env,
+        &receive_asset,
+        fee.clone(),
+        referral.clone(),
+        &recipient,
+    )?);
+    let minimum_receive = apply_max_slippage(minimum_receive, max_slippage, || {
+        let output = paths
+            .iter()
+            .try_fold(Uint128::zero(), |total, (amount, path)| {
+                Ok::<_, ContractError>(total + path.simulate(deps.as_ref(), *amount)?)
+            })?;
+        Ok(deduct_fees(output, &fee, &referral))
+    })?;
+
+    // 3. Assert min receive
+    if let Some(minimum_receive) = minimum_receive {
+        msgs.push(assert_minimum_receive_msg(
+            deps.as_ref(),
+            &env,
+            &receive_asset,
+            minimum_receive,
+            &recipient,
+        )?);
+    }
+
+    // 4. Deliver the output with the post swap action, or split it between
+    // the recipients
+    msgs.extend(post_swap_action_msg(
+        deps.as_ref(),
+        &env,
+        &receive_asset,
+        post_swap_action,
+    )?);
+    msgs.extend(distribute_received_msg(
+        deps.as_ref(),
+        &env,
+        &receive_asset,
+        recipients,
+    )?);
+
+    let event = Event::new("apollo/cw-dex-router/basket_liquidate")
+        .add_attribute("offer_assets", offer_assets.to_string())
+        .add_attribute("receive_asset", receive_asset.to_string())
+        .add_attribute("minimum_receive", minimum_receive.unwrap_or_default())
+        .add_attribute("recipient", recipient);
+
+    guard_swap(
+        deps.storage,
+        &env,
+        Response::new()
+            .add_messages(receive_msgs)
+            .add_messages(msgs)
+            .add_event(event),
+    )
+}
+
+pub fn batch_swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    swaps: Vec<BatchSwapEntry>,
+    failure_policy: BatchFailurePolicy,
+    deadline: Option<Deadline>,
+) -> Result<Response, ContractError> {
+    assert_deadline(&env, deadline)?;
+
+    if swaps.is_empty() {
+        return Err(ContractError::InvalidBatchSwap {
+            reason: "no swaps given".to_string(),
+        });
+    }
+
+    let mut offer_assets = AssetList::new();
+    let mut entries = vec![];
+    for (index, swap) in swaps.into_iter().enumerate() {
+        if swap.offer_amount.is_zero() {
+            return Err(ContractError::InvalidBatchSwap {
+                reason: format!("offer amount of swap {} is zero", index),
+            });
+        }
+        let operations = swap.operations.check(deps.as_ref())?;
+        let recipient = match swap.to {
+            Some(to) => deps.api.addr_validate(&to)?,
+            None => info.sender.clone(),
+        };
+        if recipient == env.contract.address {
+            return Err(ContractError::SwapOutputToRouter);
+        }
+        offer_assets.add(&Asset::new(operations.from(), swap.offer_amount))?;
+        entries.push((
+            operations,
+            swap.offer_amount,
+            swap.minimum_receive,
+            recipient,
+        ));
+    }
+
+    // 1. Assert the total of each native offer asset is sent, and nothing else,
+    // or do TransferFrom on Cw20s
+    let native_count = offer_assets
+        .into_iter()
+        .filter(|asset| matches!(asset.info, AssetInfo::Native(_)))
+        .count();
+    if info.funds.len() != native_count {
+        return Err(ContractError::IncorrectNativeAmountSent);
+    }
     let receive_msgs = receive_assets(&info, &env, &offer_assets)?;
 
-    // 2. Loop over offer assets and for each:
-    // Fetch path and call ExecuteMsg::ExecuteSwapOperations
-    let mut msgs = offer_assets
-        .into_iter()
-        .try_fold(vec![], |mut msgs, asset| {
-            let path = PATHS
-                .load(
-                    deps.storage,
-                    (asset.info.clone().into(), receive_asset.clone().into()),
-                )
-                .map_err(|_| ContractError::NoPathFound {
-                    offer: asset.info.to_string(),
-                    ask: receive_asset.to_string(),
-                })?;
-            msgs.extend(path.into_execute_msgs(&env, recipient.clone())?);
-            Ok::<Vec<_>, ContractError>(msgs)
-        })?;
+    // 2. Execute each swap in its own sub message. With SkipAndRefund a failing
+    // swap is reverted and its offer asset is refunded in `reply`, which finds
+    // it by the index of the swap.
+    if failure_policy == BatchFailurePolicy::SkipAndRefund {
+        BATCH_SWAP_REFUNDS.save(
+            deps.storage,
+            &BatchSwapRefunds {
+                recipient: info.sender.clone(),
+                offer_assets: entries
+                    .iter()
+                    .map(|(operations, offer_amount, _, _)| {
+                        Asset::new(operations.from(), *offer_amount)
+                    })
+                    .collect(),
+            },
+        )?;
+    }
+    let swap_count = entries.len();
+    let mut sub_msgs = vec![];
+    for (index, (operations, offer_amount, minimum_receive, recipient)) in
+        entries.into_iter().enumerate()
+    {
+        let msg = CallbackMsg::ExecuteBatchSwapEntry {
+            operations,
+            offer_amount,
+            minimum_receive,
+            recipient,
+            sender: info.sender.clone(),
+            deadline,
+        }
+        .into_cosmos_msg(&env)?;
+        sub_msgs.push(match failure_policy {
+            BatchFailurePolicy::AllOrNothing => SubMsg::new(msg),
+            BatchFailurePolicy::SkipAndRefund => SubMsg::reply_on_error(msg, index as u64),
+        });
+    }
+    if failure_policy == BatchFailurePolicy::SkipAndRefund {
+        sub_msgs.push(SubMsg::new(
+            CallbackMsg::ClearBatchSwapRefunds {}.into_cosmos_msg(&env)?,
+        ));
+    }
+
+    let event = Event::new("apollo/cw-dex-router/batch_swap")
+        .add_attribute("swaps", swap_count.to_string())
+        .add_attribute("offer_assets", offer_assets.to_string())
+        .add_attribute("failure_policy", format!("{:?}", failure_policy));
+
+    guard_swap(
+        deps.storage,
+        &env,
+        Response::new()
+            .add_messages(receive_msgs)
+            .add_submessages(sub_msgs)
+            .add_event(event),
+    )
+}
+
+pub fn place_limit_order(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    offer_asset: Asset,
+    ask_asset: AssetInfo,
+    minimum_receive: Uint128,
+    tip: Uint128,
+) -> Result<Response, ContractError> {
+    assert_no_swap_in_progress(deps.storage)?;
+    if offer_asset.amount.is_zero() || minimum_receive.is_zero() {
+        return Err(ContractError::InvalidLimitOrder {
+            reason: "offer amount and minimum receive must be non-zero".to_string(),
+        });
+    }
+    let path = best_path_for_pair(deps.as_ref(), &offer_asset, &ask_asset)?;
+
+    // Escrow the offer asset and the tip
+    let receive_msgs = receive_escrow(
+        &info,
+        &env,
+        &Asset::new(
+            offer_asset.info.clone(),
+            offer_asset.amount.checked_add(tip)?,
+        ),
+    )?;
+
+    let id = NEXT_LIMIT_ORDER_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_LIMIT_ORDER_ID.save(deps.storage, &(id + 1))?;
+    let order = LimitOrder {
+        id,
+        owner: info.sender,
+        offer_asset,
+        ask_asset,
+        path,
+        minimum_receive,
+        tip,
+    };
+    LIMIT_ORDERS.save(deps.storage, id, &order)?;
+    LIMIT_ORDERS_BY_OWNER.save(deps.storage, (&order.owner, id), &Empty {})?;
+
+    let event = Event::new("apollo/cw-dex-router/place_limit_order")
+        .add_attribute("order_id", id.to_string())
+        .add_attribute("owner", order.owner.to_string())
+        .add_attribute("offer_asset", order.offer_asset.to_string())
+        .add_attribute("ask_asset", order.ask_asset.to_string())
+        .add_attribute("minimum_receive", order.minimum_receive)
+        .add_attribute("tip", order.tip);
+
+    Ok(Response::new().add_messages(receive_msgs).add_event(event))
+}
+
+pub fn cancel_limit_order(
+    deps: DepsMut,
+    info: MessageInfo,
+    order_id: u64,
+) -> Result<Response, ContractError> {
+    assert_no_swap_in_progress(deps.storage)?;
+    let order = LIMIT_ORDERS.load(deps.storage, order_id)?;
+    if order.owner != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    remove_limit_order(deps.storage, &order);
+
+    let refund = Asset::new(
+        order.offer_asset.info.clone(),
+        order.offer_asset.amount + order.tip,
+    );
+
+    let event = Event::new("apollo/cw-dex-router/cancel_limit_order")
+        .add_attribute("order_id", order_id.to_string())
+        .add_attribute("refund", refund.to_string());
+
+    Ok(Response::new()
+        .add_message(refund.transfer_msg(&order.owner)?)
+        .add_event(event))
+}
+
+pub fn execute_limit_orders(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    order_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    assert_no_swap_in_progress(deps.storage)?;
+    let mut sub_msgs = vec![];
+    for order_id in order_ids {
+        let Some(order) = LIMIT_ORDERS.may_load(deps.storage, order_id)? else {
+            continue;
+        };
+
+        // The order is only filled if its path is enabled and simulates to at
+        // least its minimum receive. The minimum is asserted again when the
+        // swap is executed.
+        let fillable =
+            !query_path_disabled(deps.as_ref(), &order.offer_asset.info, &order.ask_asset)
+                && order
+                    .path
+                    .simulate(deps.as_ref(), order.offer_asset.amount)
+                    .is_ok_and(|output| output >= order.minimum_receive);
+        if !fillable {
+            continue;
+        }
+
+        // Each order is filled in its own sub message, after the fills before
+        // it have moved the price. An order that no longer returns its minimum
+        // receive is reverted and left in place by `reply`, without failing
+        // the other fills.
+        sub_msgs.push(SubMsg::reply_on_error(
+            CallbackMsg::FillLimitOrder {
+                order_id,
+                keeper: info.sender.clone(),
+            }
+            .into_cosmos_msg(&env)?,
+            LIMIT_ORDER_REPLY_ID_OFFSET + order_id,
+        ));
+    }
+
+    if sub_msgs.is_empty() {
+        return Err(ContractError::NoLimitOrdersFilled);
+    }
+
+    let event = Event::new("apollo/cw-dex-router/execute_limit_orders")
+        .add_attribute("orders", sub_msgs.len().to_string())
+        .add_attribute("keeper", info.sender.to_string());
+
+    guard_swap(
+        deps.storage,
+        &env,
+        Response::new().add_submessages(sub_msgs).add_event(event),
+    )
+}
+
+/// Fills limit order `order_id` and sends its tip to `keeper`. The order is
+/// removed here so that it stays in place if the fill fails.
+pub fn fill_limit_order(
+    deps: DepsMut,
+    env: Env,
+    order_id: u64,
+    keeper: Addr,
+) -> Result<Response, ContractError> {
+    let order = LIMIT_ORDERS.load(deps.storage, order_id)?;
+    remove_limit_order(deps.storage, &order);
+
+    let tip = Asset::new(order.offer_asset.info.clone(), order.tip);
+    let event = Event::new("apollo/cw-dex-router/callback_fill_limit_order")
+        .add_attribute("order_id", order_id.to_string())
+        .add_attribute("keeper", keeper.to_string())
+        .add_attribute("tip", tip.to_string());
+
+    // Executed like a swap of a batch, so that the minimum receive of the order
+    // is asserted on its own output only
+    let mut response = execute_batch_swap_entry(
+        deps,
+        env,
+        order.path,
+        order.offer_asset.amount,
+        Some(order.minimum_receive),
+        order.owner.clone(),
+        order.owner,
+        None,
+    )?
+    .add_event(event);
+    if !tip.amount.is_zero() {
+        response = response.add_message(tip.transfer_msg(&keeper)?);
+    }
+    Ok(response)
+}
+
+fn remove_limit_order(storage: &mut dyn Storage, order: &LimitOrder) {
+    LIMIT_ORDERS.remove(storage, order.id);
+    LIMIT_ORDERS_BY_OWNER.remove(storage, (&order.owner, order.id));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_dca_schedule(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    offer_asset: AssetInfo,
+    ask_asset: AssetInfo,
+    amount_per_interval: Uint128,
+    interval: u64,
+    total_budget: Uint128,
+    max_slippage: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    assert_no_swap_in_progress(deps.storage)?;
+    if amount_per_interval.is_zero() || interval == 0 || total_budget.is_zero() {
+        return Err(ContractError::InvalidDcaSchedule {
+            reason: "amount per interval, interval and total budget must be non-zero".to_string(),
+        });
+    }
+    if let Some(max_slippage) = max_slippage.filter(|x| *x > Decimal::one()) {
+        return Err(ContractError::InvalidMaxSlippage { max_slippage });
+    }
+    // Fail early if there is no path for the pair
+    assert_path_enabled(deps.as_ref(), &offer_asset, &ask_asset)?;
+    load_candidate_paths(deps.as_ref(), &offer_asset, &ask_asset)?;
+
+    let receive_msgs = receive_escrow(&info, &env, &Asset::new(offer_asset.clone(), total_budget))?;
 
-    // 3. Assert min receive
-    if let Some(minimum_receive) = minimum_receive {
-        let recipient_balance = receive_asset.query_balance(&deps.querier, recipient.clone())?;
-        msgs.push(
-            CallbackMsg::AssertMinimumReceive {
-                asset_info: receive_asset.clone(),
-                prev_balance: recipient_balance,
-                minimum_receive,
-                recipient: recipient.clone(),
-            }
-            .into_cosmos_msg(&env)?,
-        );
+    let id = NEXT_DCA_SCHEDULE_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_DCA_SCHEDULE_ID.save(deps.storage, &(id + 1))?;
+    let schedule = DcaSchedule {
+        id,
+        owner: info.sender,
+        offer_asset,
+        ask_asset,
+        amount_per_interval,
+        interval,
+        remaining: total_budget,
+        max_slippage,
+        last_triggered: None,
+        paused: false,
+    };
+    DCA_SCHEDULES.save(deps.storage, id, &schedule)?;
+    DCA_SCHEDULES_BY_OWNER.save(deps.storage, (&schedule.owner, id), &Empty {})?;
+
+    let event = Event::new("apollo/cw-dex-router/create_dca_schedule")
+        .add_attribute("schedule_id", id.to_string())
+        .add_attribute("owner", schedule.owner.to_string())
+        .add_attribute("offer_asset", schedule.offer_asset.to_string())
+        .add_attribute("ask_asset", schedule.ask_asset.to_string())
+        .add_attribute("amount_per_interval", amount_per_interval)
+        .add_attribute("interval", interval.to_string())
+        .add_attribute("total_budget", total_budget);
+
+    Ok(Response::new().add_messages(receive_msgs).add_event(event))
+}
+
+pub fn trigger_dca(deps: DepsMut, env: Env, schedule_id: u64) -> Result<Response, ContractError> {
+    assert_no_swap_in_progress(deps.storage)?;
+    let mut schedule = DCA_SCHEDULES.load(deps.storage, schedule_id)?;
+    if schedule.paused {
+        return Err(ContractError::DcaSchedulePaused { schedule_id });
+    }
+    if let Some(next) = schedule
+        .last_triggered
+        .map(|x| x.plus_seconds(schedule.interval))
+        .filter(|next| env.block.time < *next)
+    {
+        return Err(ContractError::DcaIntervalNotElapsed { schedule_id, next });
     }
 
-    let event = Event::new("apollo/cw-dex-router/basket_liquidate")
-        .add_attribute("offer_assets", offer_assets.to_string())
-        .add_attribute("receive_asset", receive_asset.to_string())
+    let slice = Asset::new(
+        schedule.offer_asset.clone(),
+        schedule.amount_per_interval.min(schedule.remaining),
+    );
+    let path = best_path_for_pair(deps.as_ref(), &slice, &schedule.ask_asset)?;
+    let fee = fee_for_caller(deps.as_ref(), &schedule.owner)?;
+    let minimum_receive = apply_max_slippage(None, schedule.max_slippage, || {
+        let output = path.simulate(deps.as_ref(), slice.amount)?;
+        Ok(deduct_fees(output, &fee, &None))
+    })?;
+
+    schedule.remaining -= slice.amount;
+    schedule.last_triggered = Some(env.block.time);
+    if schedule.remaining.is_zero() {
+        remove_dca_schedule(deps.storage, &schedule);
+    } else {
+        DCA_SCHEDULES.save(deps.storage, schedule_id, &schedule)?;
+    }
+
+    // Executed like a swap of a batch, so that only the output of this slice is
+    // counted towards its minimum receive
+    let msg = CallbackMsg::ExecuteBatchSwapEntry {
+        operations: path,
+        offer_amount: slice.amount,
+        minimum_receive,
+        recipient: schedule.owner.clone(),
+        sender: schedule.owner,
+        deadline: None,
+    }
+    .into_cosmos_msg(&env)?;
+
+    let event = Event::new("apollo/cw-dex-router/trigger_dca")
+        .add_attribute("schedule_id", schedule_id.to_string())
+        .add_attribute("offer_asset", slice.to_string())
         .add_attribute("minimum_receive", minimum_receive.unwrap_or_default())
-        .add_attribute("recipient", recipient);
+        .add_attribute("remaining", schedule.remaining);
+
+    guard_swap(
+        deps.storage,
+        &env,
+        Response::new().add_message(msg).add_event(event),
+    )
+}
+
+pub fn set_dca_schedule_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    schedule_id: u64,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    let mut schedule = DCA_SCHEDULES.load(deps.storage, schedule_id)?;
+    if schedule.owner != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    schedule.paused = paused;
+    DCA_SCHEDULES.save(deps.storage, schedule_id, &schedule)?;
+
+    let event = Event::new("apollo/cw-dex-router/set_dca_schedule_paused")
+        .add_attribute("schedule_id", schedule_id.to_string())
+        .add_attribute("paused", paused.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
+pub fn withdraw_dca_schedule(
+    deps: DepsMut,
+    info: MessageInfo,
+    schedule_id: u64,
+) -> Result<Response, ContractError> {
+    assert_no_swap_in_progress(deps.storage)?;
+    let schedule = DCA_SCHEDULES.load(deps.storage, schedule_id)?;
+    if schedule.owner != info.sender {
+        return Err(ContractError::Unauthorized);
+    }
+    remove_dca_schedule(deps.storage, &schedule);
+
+    let refund = Asset::new(schedule.offer_asset, schedule.remaining);
+
+    let event = Event::new("apollo/cw-dex-router/withdraw_dca_schedule")
+        .add_attribute("schedule_id", schedule_id.to_string())
+        .add_attribute("refund", refund.to_string());
+
+    Ok(Response::new()
+        .add_message(refund.transfer_msg(&schedule.owner)?)
+        .add_event(event))
+}
+
+fn remove_dca_schedule(storage: &mut dyn Storage, schedule: &DcaSchedule) {
+    DCA_SCHEDULES.remove(storage, schedule.id);
+    DCA_SCHEDULES_BY_OWNER.remove(storage, (&schedule.owner, schedule.id));
+}
+
+/// Handles a failed swap of a `BatchSwap` with
+/// `BatchFailurePolicy::SkipAndRefund` by refunding its offer asset. The reply
+/// id is the index of the swap in the batch. A failed limit order fill, whose
+/// reply id is offset by `LIMIT_ORDER_REPLY_ID_OFFSET`, is skipped and the
+/// order is left in place.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let error = match msg.result {
+        SubMsgResult::Err(error) => error,
+        SubMsgResult::Ok(_) => {
+            return Err(
+                StdError::generic_err(format!("Unexpected success reply id {}", msg.id)).into(),
+            )
+        }
+    };
+    if msg.id >= LIMIT_ORDER_REPLY_ID_OFFSET {
+        let event = Event::new("apollo/cw-dex-router/limit_order_fill_failed")
+            .add_attribute(
+                "order_id",
+                (msg.id - LIMIT_ORDER_REPLY_ID_OFFSET).to_string(),
+            )
+            .add_attribute("error", error);
+        return Ok(Response::new().add_event(event));
+    }
+
+    let refunds = BATCH_SWAP_REFUNDS.load(deps.storage)?;
+    let refund = refunds
+        .offer_assets
+        .get(msg.id as usize)
+        .ok_or_else(|| StdError::generic_err(format!("Unknown reply id {}", msg.id)))?;
+
+    let event = Event::new("apollo/cw-dex-router/batch_swap_entry_failed")
+        .add_attribute("index", msg.id.to_string())
+        .add_attribute("error", error)
+        .add_attribute("refund", refund.to_string())
+        .add_attribute("recipient", refunds.recipient.to_string());
 
     Ok(Response::new()
-        .add_messages(receive_msgs)
-        .add_messages(msgs)
+        .add_message(refund.transfer_msg(&refunds.recipient)?)
         .add_event(event))
 }
 
@@ -337,6 +2596,26 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             offer_amount,
             operations,
         } => to_json_binary(&simulate_swap_operations(deps, offer_amount, operations)?),
+        QueryMsg::SimulateReverseSwapOperations {
+            ask_amount,
+            operations,
+        } => to_json_binary(&simulate_reverse_swap_operations(
+            deps, ask_amount, operations,
+        )?),
+        QueryMsg::SimulateSplitRoute {
+            offer_amount,
+            route,
+        } => to_json_binary(&simulate_split_route(deps, offer_amount, route)?),
+        QueryMsg::OptimizeSplitRoute {
+            offer_amount,
+            paths,
+            steps,
+        } => to_json_binary(&query_optimize_split_route(
+            deps,
+            offer_amount,
+            paths,
+            steps,
+        )?),
         QueryMsg::SimulateBasketLiquidate {
             offer_assets,
             receive_asset,
@@ -348,109 +2627,941 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::PathForPair {
             offer_asset,
             ask_asset,
+            reference_amount,
         } => to_json_binary(&query_path_for_pair(
             deps,
             offer_asset.check(deps.api)?,
             ask_asset.check(deps.api)?,
+            reference_amount,
+        )?),
+        QueryMsg::PathDisabled {
+            offer_asset,
+            ask_asset,
+        } => to_json_binary(&query_path_disabled(
+            deps,
+            &offer_asset.check(deps.api)?,
+            &ask_asset.check(deps.api)?,
+        )),
+        QueryMsg::AllPaths {
+            start_after,
+            limit,
+            filter,
+        } => to_json_binary(&query_all_paths(deps, start_after, limit, filter)?),
+        QueryMsg::SupportedOfferAssets {
+            ask_asset,
+            start_after,
+            limit,
+        } => to_json_binary(&query_supported_offer_assets(
+            deps,
+            ask_asset,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::SupportedAskAssets {
+            offer_asset,
+            start_after,
+            limit,
+        } => to_json_binary(&query_supported_ask_assets(
+            deps,
+            offer_asset,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::BestRoute {
+            offer_asset,
+            ask_asset,
+            max_hops,
+        } => to_json_binary(&query_best_route(deps, offer_asset, ask_asset, max_hops)?),
+        QueryMsg::PathHealth {
+            reference_amount,
+            start_after,
+            limit,
+            filter,
+        } => to_json_binary(&query_path_health(
+            deps,
+            reference_amount,
+            start_after,
+            limit,
+            filter,
         )?),
-        QueryMsg::SupportedOfferAssets { ask_asset } => {
-            to_json_binary(&query_supported_offer_assets(deps, ask_asset)?)
+        QueryMsg::LimitOrder { order_id } => {
+            to_json_binary(&LIMIT_ORDERS.load(deps.storage, order_id)?)
         }
-        QueryMsg::SupportedAskAssets { offer_asset } => {
-            to_json_binary(&query_supported_ask_assets(deps, offer_asset)?)
+        QueryMsg::LimitOrdersByOwner {
+            owner,
+            start_after,
+            limit,
+        } => to_json_binary(&query_limit_orders_by_owner(
+            deps,
+            owner,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::DcaSchedule { schedule_id } => {
+            to_json_binary(&DCA_SCHEDULES.load(deps.storage, schedule_id)?)
+        }
+        QueryMsg::DcaSchedulesByOwner {
+            owner,
+            start_after,
+            limit,
+        } => to_json_binary(&query_dca_schedules_by_owner(
+            deps,
+            owner,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::FeeConfig {} => to_json_binary(&FEE_CONFIG.may_load(deps.storage)?),
+        QueryMsg::ReferralVolumes {
+            referrer,
+            start_after,
+            limit,
+        } => to_json_binary(&query_referral_volumes(deps, referrer, start_after, limit)?),
+        QueryMsg::FeeExemptCallers { start_after, limit } => {
+            to_json_binary(&query_fee_exempt_callers(deps, start_after, limit)?)
         }
+        QueryMsg::RegisteredPools {} => to_json_binary(&query_registered_pools(deps)?),
     }
 }
 
 pub fn simulate_swap_operations(
     deps: Deps,
-    mut offer_amount: Uint128,
+    offer_amount: Uint128,
+    operations: SwapOperationsListUnchecked,
+) -> Result<SimulateSwapResponse, ContractError> {
+    let gross_amount = operations.check(deps)?.simulate(deps, offer_amount)?;
+    simulate_swap_response(deps, gross_amount)
+}
+
+/// Returns the simulated output `gross_amount` before and after the protocol
+/// fee.
+fn simulate_swap_response(
+    deps: Deps,
+    gross_amount: Uint128,
+) -> Result<SimulateSwapResponse, ContractError> {
+    let fee_amount = FEE_CONFIG
+        .may_load(deps.storage)?
+        .map_or(Uint128::zero(), |fee| fee_amount(gross_amount, fee.fee_bps));
+    Ok(SimulateSwapResponse {
+        gross_amount,
+        fee_amount,
+        net_amount: gross_amount - fee_amount,
+    })
+}
+
+pub fn simulate_reverse_swap_operations(
+    deps: Deps,
+    ask_amount: Uint128,
     operations: SwapOperationsListUnchecked,
 ) -> Result<Uint128, ContractError> {
-    let operations = operations.check(deps)?;
+    let fee = FEE_CONFIG.may_load(deps.storage)?;
+    operations
+        .check(deps)?
+        .simulate_reverse(deps, amount_before_fee(ask_amount, &fee)?)
+}
 
-    for operation in operations.into_iter() {
-        let offer_asset = Asset::new(operation.offer_asset_info, offer_amount);
+pub fn simulate_split_route(
+    deps: Deps,
+    offer_amount: Uint128,
+    route: SplitRouteUnchecked,
+) -> Result<SimulateSwapResponse, ContractError> {
+    simulate_swap_response(deps, route.check(deps)?.simulate(deps, offer_amount)?)
+}
 
-        offer_amount = operation
-            .pool
-            .simulate_swap(deps, offer_asset, operation.ask_asset_info)?;
+pub fn query_optimize_split_route(
+    deps: Deps,
+    offer_amount: Uint128,
+    paths: Vec<SwapOperationsListUnchecked>,
+    steps: Option<u32>,
+) -> Result<SplitRouteResponse, ContractError> {
+    let paths = paths
+        .iter()
+        .map(|path| path.check(deps))
+        .collect::<Result<Vec<_>, _>>()?;
+    if paths
+        .iter()
+        .any(|path| path.from() != paths[0].from() || path.to() != paths[0].to())
+    {
+        return Err(ContractError::InvalidSplitRoute {
+            reason: "all paths must swap between the same assets".to_string(),
+        });
     }
 
-    Ok(offer_amount)
+    optimize_split(deps, paths, offer_amount, steps)
 }
 
 pub fn simulate_basket_liquidate(
     deps: Deps,
     offer_assets: AssetListUnchecked,
     receive_asset: AssetInfoUnchecked,
-) -> Result<Uint128, ContractError> {
+) -> Result<SimulateSwapResponse, ContractError> {
     let offer_assets = offer_assets.check(deps.api)?;
     let receive_asset = receive_asset.check(deps.api)?;
 
     let mut receive_amount = Uint128::zero();
 
-    // Loop over offer assets and fetch path for each
-    let paths = offer_assets
-        .into_iter()
-        .map(|asset| {
-            Ok::<_, ContractError>((
-                asset.clone(),
-                query_path_for_pair(deps, asset.info.clone(), receive_asset.clone())?,
-            ))
-        })
-        .collect::<Result<Vec<(Asset, SwapOperationsList)>, _>>()?;
-
-    // Loop over paths and simulate swap operations
-    for (asset, path) in paths {
-        receive_amount += simulate_swap_operations(deps, asset.amount, path.into())?;
+    // Loop over offer assets and simulate the best candidate path for each
+    for asset in offer_assets.into_iter() {
+        assert_path_enabled(deps, &asset.info, &receive_asset)?;
+        let best = query_path_for_pair(
+            deps,
+            asset.info.clone(),
+            receive_asset.clone(),
+            Some(asset.amount),
+        )?
+        .remove(0);
+        receive_amount += match best.simulated_output {
+            Some(amount) => amount,
+            // None of the candidates could be simulated, simulate again to
+            // return the error
+            None => best.operations.simulate(deps, asset.amount)?,
+        };
     }
 
-    Ok(receive_amount)
+    simulate_swap_response(deps, receive_amount)
 }
 
 pub fn query_path_for_pair(
     deps: Deps,
     offer_asset: AssetInfo,
     ask_asset: AssetInfo,
+    reference_amount: Option<Uint128>,
+) -> Result<Vec<PathCandidate>, ContractError> {
+    let candidates = load_candidate_paths(deps, &offer_asset, &ask_asset)?;
+
+    match reference_amount {
+        Some(amount) => Ok(rank_candidate_paths(deps, candidates, amount)),
+        None => Ok(candidates
+            .into_iter()
+            .map(|operations| PathCandidate {
+                operations,
+                simulated_output: None,
+            })
+            .collect()),
+    }
+}
+
+/// Returns the stored candidate path for the pair that is simulated to return
+/// the most ask asset for `offer_asset`. If none of the candidates can be
+/// simulated the first one is returned, so that the swap fails with the error
+/// from the pool.
+pub fn best_path_for_pair(
+    deps: Deps,
+    offer_asset: &Asset,
+    ask_asset: &AssetInfo,
 ) -> Result<SwapOperationsList, ContractError> {
-    PATHS
-        .load(deps.storage, ((&offer_asset).into(), (&ask_asset).into()))
-        .map_err(|_| ContractError::NoPathFound {
+    assert_path_enabled(deps, &offer_asset.info, ask_asset)?;
+    let mut candidates = load_candidate_paths(deps, &offer_asset.info, ask_asset)?;
+
+    // No need to simulate if there is nothing to choose from
+    if candidates.len() == 1 {
+        return Ok(candidates.remove(0));
+    }
+
+    Ok(rank_candidate_paths(deps, candidates, offer_asset.amount)
+        .remove(0)
+        .operations)
+}
+
+fn assert_path_enabled(
+    deps: Deps,
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+) -> Result<(), ContractError> {
+    if query_path_disabled(deps, offer_asset, ask_asset) {
+        return Err(ContractError::PathDisabled {
+            offer: offer_asset.to_string(),
+            ask: ask_asset.to_string(),
+        });
+    }
+    Ok(())
+}
+
+pub fn query_path_disabled(deps: Deps, offer_asset: &AssetInfo, ask_asset: &AssetInfo) -> bool {
+    DISABLED_PATHS.has(deps.storage, (offer_asset.into(), ask_asset.into()))
+}
+
+/// Returns the stored candidate paths for the pair, or if there are none, the
+/// paths built through the hub assets.
+fn load_candidate_paths(
+    deps: Deps,
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+) -> Result<Vec<SwapOperationsList>, ContractError> {
+    let mut candidates = PATHS
+        .may_load(deps.storage, (offer_asset.into(), ask_asset.into()))?
+        .unwrap_or_default();
+    if candidates.is_empty() {
+        candidates = compose_paths_through_hubs(deps, offer_asset, ask_asset)?;
+    }
+
+    if candidates.is_empty() {
+        return Err(ContractError::NoPathFound {
             offer: offer_asset.to_string(),
             ask: ask_asset.to_string(),
+        });
+    }
+    Ok(candidates)
+}
+
+/// Builds candidate paths for the pair by joining each stored path from
+/// `offer_asset` to a hub asset with each stored path from the hub asset to
+/// `ask_asset`. Joined paths that break the `SwapOperationsList` rules, such as
+/// swapping through the same pool twice, are skipped, as are disabled pairs.
+fn compose_paths_through_hubs(
+    deps: Deps,
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+) -> StdResult<Vec<SwapOperationsList>> {
+    let config = CONFIG.may_load(deps.storage)?.unwrap_or_default();
+
+    let mut candidates = vec![];
+    for hub in config.hub_assets.iter() {
+        if hub == offer_asset
+            || hub == ask_asset
+            || query_path_disabled(deps, offer_asset, hub)
+            || query_path_disabled(deps, hub, ask_asset)
+        {
+            continue;
+        }
+
+        let first_legs = PATHS
+            .may_load(deps.storage, (offer_asset.into(), hub.into()))?
+            .unwrap_or_default();
+        let second_legs = PATHS
+            .may_load(deps.storage, (hub.into(), ask_asset.into()))?
+            .unwrap_or_default();
+        for first in first_legs.iter() {
+            for second in second_legs.iter() {
+                if let Ok(path) = first.join(second) {
+                    if !candidates.contains(&path) {
+                        candidates.push(path);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Simulates each candidate with `offer_amount` and sorts them by output,
+/// best first. Candidates that fail to simulate are put last.
+fn rank_candidate_paths(
+    deps: Deps,
+    candidates: Vec<SwapOperationsList>,
+    offer_amount: Uint128,
+) -> Vec<PathCandidate> {
+    let mut ranked = candidates
+        .into_iter()
+        .map(|operations| PathCandidate {
+            simulated_output: operations.simulate(deps, offer_amount).ok(),
+            operations,
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by_key(|candidate| Reverse(candidate.simulated_output));
+    ranked
+}
+
+pub fn query_all_paths(
+    deps: Deps,
+    start_after: Option<(AssetInfoUnchecked, AssetInfoUnchecked)>,
+    limit: Option<u32>,
+    filter: Option<PathFilter>,
+) -> Result<AllPathsResponse, ContractError> {
+    let filter = filter.unwrap_or_default();
+    let offer_asset = filter.offer_asset.map(|x| x.check(deps.api)).transpose()?;
+    let ask_asset = filter.ask_asset.map(|x| x.check(deps.api)).transpose()?;
+    let start_after = start_after
+        .map(|(offer, ask)| Ok::<_, ContractError>((offer.check(deps.api)?, ask.check(deps.api)?)))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // Only read the pairs of the filtered offer or ask asset, if any
+    type PairPaths = ((AssetInfo, AssetInfo), Vec<SwapOperationsList>);
+    let pairs: Box<dyn Iterator<Item = StdResult<PairPaths>>> = match (&offer_asset, &ask_asset) {
+        (Some(offer_asset), Some(ask_asset)) => {
+            let pair = (offer_asset.clone(), ask_asset.clone());
+            if start_after.as_ref() == Some(&pair) {
+                Box::new(std::iter::empty())
+            } else {
+                let candidates =
+                    PATHS.may_load(deps.storage, (offer_asset.into(), ask_asset.into()))?;
+                Box::new(
+                    candidates
+                        .map(|candidates| Ok((pair, candidates)))
+                        .into_iter(),
+                )
+            }
+        }
+        (Some(offer_asset), None) => {
+            let start = start_after
+                .filter(|(offer, _)| offer == offer_asset)
+                .map(|(_, ask)| Bound::exclusive(AssetInfoKey::from(ask)));
+            let offer_asset = offer_asset.clone();
+            Box::new(
+                PATHS
+                    .prefix((&offer_asset).into())
+                    .range(deps.storage, start, None, Order::Ascending)
+                    .map(move |x| {
+                        x.map(|(ask, candidates)| ((offer_asset.clone(), ask), candidates))
+                    }),
+            )
+        }
+        (None, Some(ask_asset)) => {
+            let start = start_after
+                .filter(|(_, ask)| ask == ask_asset)
+                .map(|(offer, _)| Bound::exclusive(AssetInfoKey::from(offer)));
+            let ask_asset = ask_asset.clone();
+            Box::new(
+                PATHS_BY_ASK_ASSET
+                    .prefix((&ask_asset).into())
+                    .keys(deps.storage, start, None, Order::Ascending)
+                    .map(move |offer| {
+                        let offer = offer?;
+                        let candidates =
+                            PATHS.load(deps.storage, ((&offer).into(), (&ask_asset).into()))?;
+                        Ok(((offer, ask_asset.clone()), candidates))
+                    }),
+            )
+        }
+        (None, None) => {
+            let start = start_after.map(|(offer, ask)| {
+                Bound::exclusive((AssetInfoKey::from(offer), AssetInfoKey::from(ask)))
+            });
+            Box::new(PATHS.range(deps.storage, start, None, Order::Ascending))
+        }
+    };
+
+    let mut pairs = pairs.peekable();
+    let mut paths = vec![];
+    let mut pairs_returned = 0;
+    let mut last_scanned = None;
+    for _ in 0..MAX_SCANNED_PAIRS {
+        if pairs_returned >= limit {
+            break;
+        }
+        let Some(x) = pairs.next() else {
+            break;
+        };
+        let ((path_offer_asset, path_ask_asset), candidates) = x?;
+        last_scanned = Some((path_offer_asset.clone(), path_ask_asset.clone()));
+
+        let disabled = query_path_disabled(deps, &path_offer_asset, &path_ask_asset);
+        let matching = candidates
+            .into_iter()
+            .filter(|path| {
+                filter.hops.is_none_or(|hops| path.hops() == hops as usize)
+                    && filter
+                        .pool
+                        .as_ref()
+                        .is_none_or(|pool| path.contains_pool(pool))
+            })
+            .map(|path| PathEntry {
+                offer_asset: path_offer_asset.clone(),
+                ask_asset: path_ask_asset.clone(),
+                path,
+                disabled,
+            })
+            .collect::<Vec<_>>();
+        if !matching.is_empty() {
+            paths.extend(matching);
+            pairs_returned += 1;
+        }
+    }
+
+    // Continue after the last pair read if the page is full or the scan
+    // stopped before all pairs were read
+    let next_start_after = pairs.peek().and(last_scanned);
+    Ok(AllPathsResponse {
+        paths,
+        next_start_after,
+    })
+}
+
+pub fn query_path_health(
+    deps: Deps,
+    reference_amount: Uint128,
+    start_after: Option<(AssetInfoUnchecked, AssetInfoUnchecked)>,
+    limit: Option<u32>,
+    filter: Option<PathFilter>,
+) -> Result<PathHealthResponse, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_HEALTH_LIMIT).min(MAX_HEALTH_LIMIT);
+
+    let all_paths = query_all_paths(deps, start_after, Some(limit), filter)?;
+    let paths = all_paths
+        .paths
+        .into_iter()
+        .map(|entry| {
+            let (hops, return_amount) = simulate_hops(deps, &entry.path, reference_amount);
+            PathHealth {
+                offer_asset: entry.offer_asset,
+                ask_asset: entry.ask_asset,
+                path: entry.path,
+                disabled: entry.disabled,
+                hops,
+                return_amount,
+            }
         })
+        .collect();
+    Ok(PathHealthResponse {
+        paths,
+        next_start_after: all_paths.next_start_after,
+    })
 }
 
 pub fn query_supported_offer_assets(
     deps: Deps,
     ask_asset: AssetInfoUnchecked,
+    start_after: Option<AssetInfoUnchecked>,
+    limit: Option<u32>,
 ) -> Result<Vec<AssetInfo>, ContractError> {
-    let mut offer_assets: Vec<AssetInfo> = vec![];
-    for x in PATHS.range(deps.storage, None, None, Order::Ascending) {
-        let ((offer_asset, path_ask_asset), _) = x?;
-        if path_ask_asset == ask_asset.check(deps.api)? {
-            offer_assets.push(offer_asset.into());
-        }
-    }
-    Ok(offer_assets)
+    let ask_asset = ask_asset.check(deps.api)?;
+    let start = start_after
+        .map(|x| x.check(deps.api))
+        .transpose()?
+        .map(|x| Bound::exclusive(AssetInfoKey::from(&x)));
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    Ok(PATHS_BY_ASK_ASSET
+        .prefix((&ask_asset).into())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?)
 }
 
 pub fn query_supported_ask_assets(
     deps: Deps,
     offer_asset: AssetInfoUnchecked,
+    start_after: Option<AssetInfoUnchecked>,
+    limit: Option<u32>,
 ) -> Result<Vec<AssetInfo>, ContractError> {
-    let mut ask_assets: Vec<AssetInfo> = vec![];
-    for x in PATHS.range(deps.storage, None, None, Order::Ascending) {
-        let ((path_offer_asset, ask_asset), _) = x?;
-        if path_offer_asset == offer_asset.check(deps.api)? {
-            ask_assets.push(ask_asset.into());
-        }
-    }
-    Ok(ask_assets)
+    let offer_asset = offer_asset.check(deps.api)?;
+    let start = start_after
+        .map(|x| x.check(deps.api))
+        .transpose()?
+        .map(|x| Bound::exclusive(AssetInfoKey::from(&x)));
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    Ok(PATHS
+        .prefix((&offer_asset).into())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?)
+}
+
+pub fn query_best_route(
+    deps: Deps,
+    offer_asset: AssetUnchecked,
+    ask_asset: AssetInfoUnchecked,
+    max_hops: Option<u32>,
+) -> Result<BestRouteResponse, ContractError> {
+    let offer_asset = offer_asset.check(deps.api)?;
+    find_best_route(
+        deps,
+        &offer_asset.info,
+        offer_asset.amount,
+        &ask_asset.check(deps.api)?,
+        max_hops,
+    )
+}
+
+pub fn query_limit_orders_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<LimitOrder>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    LIMIT_ORDERS_BY_OWNER
+        .prefix(&owner)
+        .keys(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|id| LIMIT_ORDERS.load(deps.storage, id?))
+        .collect()
+}
+
+pub fn query_dca_schedules_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<DcaSchedule>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    DCA_SCHEDULES_BY_OWNER
+        .prefix(&owner)
+        .keys(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|id| DCA_SCHEDULES.load(deps.storage, id?))
+        .collect()
+}
+
+pub fn query_referral_volumes(
+    deps: Deps,
+    referrer: String,
+    start_after: Option<AssetInfoUnchecked>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Asset>> {
+    let referrer = deps.api.addr_validate(&referrer)?;
+    let start = start_after
+        .map(|x| x.check(deps.api))
+        .transpose()?
+        .map(|x| Bound::exclusive(AssetInfoKey::from(x)));
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    REFERRAL_VOLUMES
+        .prefix(&referrer)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|x| x.map(|(asset_info, volume)| Asset::new(asset_info, volume)))
+        .collect()
+}
+
+pub fn query_fee_exempt_callers(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Addr>> {
+    let start_after = start_after
+        .map(|x| deps.api.addr_validate(&x))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    FEE_EXEMPT_CALLERS
+        .keys(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect()
+}
+
+pub fn query_config(deps: Deps) -> StdResult<Config> {
+    Ok(CONFIG.may_load(deps.storage)?.unwrap_or_default())
+}
+
+pub fn query_registered_pools(deps: Deps) -> Result<Vec<RegisteredPool>, ContractError> {
+    Ok(REGISTERED_POOLS.may_load(deps.storage)?.unwrap_or_default())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    // Versions up to 0.5.0 stored a single path per pair. Move them over to
+    // the candidate paths.
+    let legacy_paths = LEGACY_PATHS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for ((offer_asset, ask_asset), path) in legacy_paths {
+        save_candidates(deps.storage, &offer_asset, &ask_asset, &vec![path])?;
+        LEGACY_PATHS.remove(deps.storage, ((&offer_asset).into(), (&ask_asset).into()));
+    }
+
+    // Index the ask assets of paths stored before the index was added
+    let pairs = PATHS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (offer_asset, ask_asset) in pairs {
+        PATHS_BY_ASK_ASSET.save(
+            deps.storage,
+            ((&ask_asset).into(), (&offer_asset).into()),
+            &Empty {},
+        )?;
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     Ok(Response::default())
 }
+
+#[cfg(feature = "osmosis")]
+#[cfg(test)]
+mod unit_tests {
+    use super::{
+        execute, execute_swap_operations, query_all_paths, refund_leftovers, refund_leftovers_msg,
+        reply, MAX_SCANNED_PAIRS,
+    };
+    use crate::msg::{CallbackMsg, Cw20HookMsg, ExecuteMsg, PathFilter};
+    use crate::operations::{SwapOperation, SwapOperationsList};
+    use crate::pool::Pool;
+    use crate::state::PATHS;
+    use crate::ContractError;
+    use apollo_cw_asset::{Asset, AssetInfo, AssetInfoUnchecked, AssetList};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
+    };
+    use cosmwasm_std::{
+        coin, to_json_binary, Addr, BankMsg, CosmosMsg, Reply, SubMsgResponse, SubMsgResult,
+        Uint128,
+    };
+    use cw20::Cw20ReceiveMsg;
+    use cw_dex_osmosis::OsmosisPool;
+
+    fn op(pool_id: u64, offer: &str, ask: &str) -> SwapOperation {
+        SwapOperation::new(
+            Pool::Osmosis(OsmosisPool::unchecked(pool_id)),
+            AssetInfo::Native(offer.to_string()),
+            AssetInfo::Native(ask.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_refund_leftovers_msg_excludes_offer_and_ask_assets() {
+        let deps = mock_dependencies_with_balance(&[coin(100, "asset2"), coin(200, "asset3")]);
+        let env = mock_env();
+        let recipient = Addr::unchecked("recipient");
+
+        // The path swaps through its ask asset, which must not be refunded
+        let path = SwapOperationsList::new(vec![
+            op(1, "asset1", "asset3"),
+            op(2, "asset3", "asset2"),
+            op(3, "asset2", "asset3"),
+        ]);
+        let msg = refund_leftovers_msg(deps.as_ref(), &env, [&path], &recipient).unwrap();
+        assert_eq!(
+            msg,
+            Some(
+                CallbackMsg::RefundLeftovers {
+                    prev_balances: AssetList::from(vec![Asset::native("asset2", 100u128)]),
+                    recipient: recipient.clone(),
+                }
+                .into_cosmos_msg(&env)
+                .unwrap()
+            )
+        );
+
+        // No intermediate assets besides the ask asset
+        let path = SwapOperationsList::new(vec![
+            op(1, "asset1", "asset3"),
+            op(2, "asset3", "asset1"),
+            op(3, "asset1", "asset3"),
+        ]);
+        let msg = refund_leftovers_msg(deps.as_ref(), &env, [&path], &recipient).unwrap();
+        assert_eq!(msg, None);
+    }
+
+    #[test]
+    fn test_refund_leftovers() {
+        let mut deps = mock_dependencies_with_balance(&[coin(150, "asset2")]);
+        let recipient = Addr::unchecked("recipient");
+
+        let res = refund_leftovers(
+            deps.as_mut(),
+            mock_env(),
+            AssetList::from(vec![
+                Asset::native("asset2", 100u128),
+                Asset::native("asset3", 0u128),
+            ]),
+            recipient.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            res.messages
+                .into_iter()
+                .map(|msg| msg.msg)
+                .collect::<Vec<_>>(),
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![coin(50, "asset2")],
+            })]
+        );
+        assert_eq!(res.events.len(), 1);
+        let event = &res.events[0];
+        assert_eq!(event.ty, "apollo/cw-dex-router/refund_leftovers");
+        let attribute = |key: &str| {
+            event
+                .attributes
+                .iter()
+                .find(|attr| attr.key == key)
+                .map(|attr| attr.value.clone())
+        };
+        assert_eq!(
+            attribute("assets"),
+            Some(AssetList::from(vec![Asset::native("asset2", 50u128)]).to_string())
+        );
+        assert_eq!(attribute("recipient"), Some(recipient.to_string()));
+    }
+
+    #[test]
+    fn test_query_all_paths_continues_after_max_scanned_pairs() {
+        let mut deps = mock_dependencies();
+        let ask_asset = AssetInfo::Native("ask".to_string());
+        let offer_asset = |index: usize| AssetInfo::Native(format!("offer{:04}", index));
+
+        // Only one pair, after the first page of scanned pairs, swaps through
+        // the filtered pool
+        let matching = MAX_SCANNED_PAIRS + 5;
+        for index in 0..MAX_SCANNED_PAIRS + 10 {
+            let pool_id = if index == matching { 2 } else { 1 };
+            let path =
+                SwapOperationsList::new(vec![op(pool_id, &format!("offer{:04}", index), "ask")]);
+            PATHS
+                .save(
+                    deps.as_mut().storage,
+                    ((&offer_asset(index)).into(), (&ask_asset).into()),
+                    &vec![path],
+                )
+                .unwrap();
+        }
+        let filter = PathFilter {
+            pool: Some(Pool::Osmosis(OsmosisPool::unchecked(2))),
+            ..Default::default()
+        };
+
+        // The first page is empty but can be continued from
+        let page = query_all_paths(deps.as_ref(), None, None, Some(filter.clone())).unwrap();
+        assert!(page.paths.is_empty());
+        assert_eq!(
+            page.next_start_after,
+            Some((offer_asset(MAX_SCANNED_PAIRS - 1), ask_asset.clone()))
+        );
+
+        let start_after = page
+            .next_start_after
+            .map(|(offer, ask)| (offer.into(), ask.into()));
+        let page = query_all_paths(deps.as_ref(), start_after, None, Some(filter)).unwrap();
+        assert_eq!(
+            page.paths
+                .iter()
+                .map(|entry| entry.offer_asset.clone())
+                .collect::<Vec<_>>(),
+            vec![offer_asset(matching)]
+        );
+        assert_eq!(page.next_start_after, None);
+    }
+
+    #[test]
+    fn test_reply_on_success_fails() {
+        let mut deps = mock_dependencies();
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: 0,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+        );
+        assert!(matches!(res, Err(ContractError::Std(_))));
+    }
+
+    #[test]
+    fn test_execute_swap_operations_cw20_missing_offer_amount() {
+        let mut deps = mock_dependencies();
+        let info = mock_info("sender", &[]);
+        let operations = SwapOperationsList::new(vec![SwapOperation::new(
+            Pool::Osmosis(OsmosisPool::unchecked(1)),
+            AssetInfo::Cw20(Addr::unchecked("cw20")),
+            AssetInfo::Native("asset2".to_string()),
+        )]);
+
+        let err = execute_swap_operations(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            info.sender,
+            operations,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::MissingOfferAmount));
+    }
+
+    #[test]
+    fn test_receive_cw20_uses_received_amount() {
+        let hooks = [
+            Cw20HookMsg::BasketLiquidate {
+                receive_asset: AssetInfoUnchecked::Native("asset2".to_string()),
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            Cw20HookMsg::SwapStoredPath {
+                ask_asset: AssetInfoUnchecked::Native("asset2".to_string()),
+                minimum_receive: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+            },
+        ];
+        for hook in hooks {
+            let mut deps = mock_dependencies();
+            let env = mock_env();
+            let cw20 = AssetInfo::Cw20(Addr::unchecked("cw20"));
+            let operation = SwapOperation::new(
+                Pool::Osmosis(OsmosisPool::unchecked(1)),
+                cw20.clone(),
+                AssetInfo::Native("asset2".to_string()),
+            );
+            PATHS
+                .save(
+                    deps.as_mut().storage,
+                    ((&cw20).into(), (&operation.ask_asset_info).into()),
+                    &vec![SwapOperationsList::new(vec![operation.clone()])],
+                )
+                .unwrap();
+
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("cw20", &[]),
+                ExecuteMsg::Receive(Cw20ReceiveMsg {
+                    sender: "sender".to_string(),
+                    amount: Uint128::new(1_000),
+                    msg: to_json_binary(&hook).unwrap(),
+                }),
+            )
+            .unwrap();
+
+            // The received amount is swapped, and it is not transferred from
+            // the sender again
+            assert_eq!(
+                res.messages
+                    .into_iter()
+                    .map(|msg| msg.msg)
+                    .collect::<Vec<_>>(),
+                vec![
+                    CallbackMsg::ExecuteSwapOperation {
+                        operation,
+                        offer_amount: Uint128::new(1_000),
+                        next_operations: vec![],
+                        to: Addr::unchecked("sender"),
+                        deadline: None,
+                    }
+                    .into_cosmos_msg(&env)
+                    .unwrap(),
+                    CallbackMsg::FinishSwap {}.into_cosmos_msg(&env).unwrap(),
+                ]
+            );
+        }
+    }
+}
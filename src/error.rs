@@ -1,8 +1,9 @@
-use cosmwasm_std::{OverflowError, StdError};
+use cosmwasm_std::{Decimal, OverflowError, StdError, Timestamp, Uint128};
 use cw_controllers::AdminError;
 use cw_dex::CwDexError;
 use thiserror::Error;
 
+use crate::msg::Deadline;
 use crate::operations::SwapOperation;
 
 #[derive(Error, Debug)]
@@ -19,9 +20,12 @@ pub enum ContractError {
     #[error("{0}")]
     AdminError(#[from] AdminError),
 
-    #[error("Incorrect amount of native token sent. You don't need to pass in offer_amount if using native tokens.")]
+    #[error("Incorrect amount of native token sent. Only the native offer asset may be sent, matching offer_amount if given.")]
     IncorrectNativeAmountSent,
 
+    #[error("offer_amount must be given when the offer asset is a Cw20")]
+    MissingOfferAmount,
+
     #[error("Unsupported asset type. Only native and cw20 tokens are supported.")]
     UnsupportedAssetType,
 
@@ -39,6 +43,79 @@ pub enum ContractError {
 
     #[error("No path found for assets {offer:?} -> {ask:?}")]
     NoPathFound { offer: String, ask: String },
+
+    #[error("Route search found more than {max_routes} routes, try fewer hops")]
+    TooManyRoutes { max_routes: usize },
+
+    #[error("Route search tried more than {max_steps} swaps, try fewer hops")]
+    RouteSearchTooLarge { max_steps: usize },
+
+    #[error("Can not register more than {max_pools} pools")]
+    TooManyRegisteredPools { max_pools: usize },
+
+    #[error("Path for assets {offer:?} -> {ask:?} returns nothing for {offer_amount}")]
+    ZeroPathOutput {
+        offer: String,
+        ask: String,
+        offer_amount: Uint128,
+    },
+
+    #[error("Path for assets {offer:?} -> {ask:?} is disabled")]
+    PathDisabled { offer: String, ask: String },
+
+    #[error("Invalid split route: {reason}")]
+    InvalidSplitRoute { reason: String },
+
+    #[error("Unable to receive {ask_amount} {ask_asset} from the pool")]
+    InsufficientLiquidity {
+        ask_asset: String,
+        ask_amount: Uint128,
+    },
+
+    #[error("Max slippage {max_slippage} must not be greater than 1")]
+    InvalidMaxSlippage { max_slippage: Decimal },
+
+    #[error("Offer amount {offer_amount} exceeds max offer {max_offer}")]
+    MaxOfferExceeded {
+        offer_amount: Uint128,
+        max_offer: Uint128,
+    },
+
+    #[error("Deadline {deadline} has passed")]
+    DeadlinePassed { deadline: Deadline },
+
+    #[error("Only one of `to`, `post_swap_action` and `recipients` can be set")]
+    MultipleSwapOutputs,
+
+    #[error("The router can not be the recipient or the post swap action contract of a swap")]
+    SwapOutputToRouter,
+
+    #[error("Invalid recipients: {reason}")]
+    InvalidRecipients { reason: String },
+
+    #[error("Invalid batch swap: {reason}")]
+    InvalidBatchSwap { reason: String },
+
+    #[error("Invalid limit order: {reason}")]
+    InvalidLimitOrder { reason: String },
+
+    #[error("None of the limit orders can be filled")]
+    NoLimitOrdersFilled,
+
+    #[error("Escrowed assets can not be moved while a swap is executed")]
+    SwapInProgress,
+
+    #[error("Invalid DCA schedule: {reason}")]
+    InvalidDcaSchedule { reason: String },
+
+    #[error("DCA schedule {schedule_id} is paused")]
+    DcaSchedulePaused { schedule_id: u64 },
+
+    #[error("Next slice of DCA schedule {schedule_id} can not be swapped before {next}")]
+    DcaIntervalNotElapsed { schedule_id: u64, next: Timestamp },
+
+    #[error("Fee of {fee_bps} basis points is above the maximum of {max_fee_bps}")]
+    InvalidFee { fee_bps: u16, max_fee_bps: u16 },
 }
 
 impl From<ContractError> for StdError {
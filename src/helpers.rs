@@ -6,12 +6,17 @@ use cosmwasm_schema::cw_serde;
 use cw20::{Cw20Coin, Cw20ExecuteMsg};
 
 use cosmwasm_std::{
-    to_json_binary, Addr, Api, Coin, CosmosMsg, Env, MessageInfo, QuerierWrapper, QueryRequest,
-    StdError, StdResult, Uint128, WasmMsg, WasmQuery,
+    to_json_binary, Addr, Api, Coin, CosmosMsg, Decimal, Env, MessageInfo, QuerierWrapper,
+    QueryRequest, StdError, StdResult, Uint128, WasmMsg, WasmQuery,
 };
 
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::msg::{
+    AllPathsResponse, BatchFailurePolicy, BatchSwapEntry, Deadline, ExecuteMsg, InstantiateMsg,
+    PathCandidate, PathFilter, PostSwapAction, QueryMsg, Referral, SimulateSwapResponse,
+};
 use crate::operations::SwapOperationsList;
+use crate::routing::{BestRouteResponse, PathHealthResponse};
+use crate::state::Config;
 
 #[cw_serde]
 pub struct CwDexRouterBase<T>(pub T);
@@ -74,12 +79,18 @@ impl CwDexRouter {
         .into())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn execute_swap_operations_msg(
         &self,
         operations: &SwapOperationsList,
         offer_amount: Option<Uint128>,
         minimum_receive: Option<Uint128>,
+        max_slippage: Option<Decimal>,
         to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+        recipients: Option<Vec<(String, Decimal)>>,
+        referral: Option<Referral>,
         funds: Vec<Coin>,
     ) -> StdResult<CosmosMsg> {
         self.call(
@@ -87,7 +98,36 @@ impl CwDexRouter {
                 operations: operations.into(),
                 offer_amount,
                 minimum_receive,
+                max_slippage,
                 to,
+                deadline,
+                post_swap_action,
+                recipients,
+                referral,
+            },
+            funds,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_swap_operations_exact_out_msg(
+        &self,
+        operations: &SwapOperationsList,
+        ask_amount: Uint128,
+        max_offer: Uint128,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::ExecuteSwapOperationsExactOut {
+                operations: operations.into(),
+                ask_amount,
+                max_offer,
+                to,
+                deadline,
+                post_swap_action,
             },
             funds,
         )
@@ -96,12 +136,18 @@ impl CwDexRouter {
     /// Returns message to call BasketLiquidate, as well as approve spend on any
     /// CW20s in `offer_assets`. Also takes care of sending native tokens in
     /// `offer_assets` to the contract via the funds field.
+    #[allow(clippy::too_many_arguments)]
     pub fn basket_liquidate_msgs(
         &self,
         offer_assets: AssetList,
         receive_asset: &AssetInfo,
         minimum_receive: Option<Uint128>,
+        max_slippage: Option<Decimal>,
         to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+        recipients: Option<Vec<(String, Decimal)>>,
+        referral: Option<Referral>,
     ) -> StdResult<Vec<CosmosMsg>> {
         //Extract all native tokens to send in funds field.
         let (funds, _) = separate_natives_and_cw20s(&offer_assets);
@@ -134,7 +180,12 @@ impl CwDexRouter {
                 offer_assets: offer_assets.into(),
                 receive_asset: receive_asset.to_owned().into(),
                 minimum_receive,
+                max_slippage,
                 to,
+                deadline,
+                post_swap_action,
+                recipients,
+                referral,
             },
             funds,
         )?;
@@ -149,6 +200,8 @@ impl CwDexRouter {
         ask_asset: AssetInfo,
         path: &SwapOperationsList,
         bidirectional: bool,
+        append: bool,
+        reference_amount: Option<Uint128>,
     ) -> StdResult<CosmosMsg> {
         self.call(
             ExecuteMsg::SetPath {
@@ -156,17 +209,169 @@ impl CwDexRouter {
                 ask_asset: ask_asset.into(),
                 path: path.into(),
                 bidirectional,
+                append,
+                reference_amount,
             },
             vec![],
         )
     }
 
+    pub fn remove_path_msg(
+        &self,
+        offer_asset: AssetInfo,
+        ask_asset: AssetInfo,
+        path: Option<&SwapOperationsList>,
+        bidirectional: bool,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::RemovePath {
+                offer_asset: offer_asset.into(),
+                ask_asset: ask_asset.into(),
+                path: path.map(Into::into),
+                bidirectional,
+            },
+            vec![],
+        )
+    }
+
+    pub fn update_config_msg(
+        &self,
+        hub_assets: Option<Vec<AssetInfo>>,
+        max_referral_fee_bps: Option<u16>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::UpdateConfig {
+                hub_assets: hub_assets.map(|x| x.into_iter().map(Into::into).collect()),
+                max_referral_fee_bps,
+            },
+            vec![],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_stored_path_msg(
+        &self,
+        offer_asset: Asset,
+        ask_asset: &AssetInfo,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::SwapStoredPath {
+                offer_asset: offer_asset.into(),
+                ask_asset: ask_asset.to_owned().into(),
+                minimum_receive,
+                to,
+                deadline,
+                post_swap_action,
+            },
+            funds,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_best_route_msg(
+        &self,
+        offer_asset: Asset,
+        ask_asset: &AssetInfo,
+        max_hops: Option<u32>,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::SwapBestRoute {
+                offer_asset: offer_asset.into(),
+                ask_asset: ask_asset.to_owned().into(),
+                max_hops,
+                minimum_receive,
+                to,
+                deadline,
+                post_swap_action,
+            },
+            funds,
+        )
+    }
+
+    pub fn batch_swap_msg(
+        &self,
+        swaps: Vec<BatchSwapEntry>,
+        failure_policy: BatchFailurePolicy,
+        deadline: Option<Deadline>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::BatchSwap {
+                swaps,
+                failure_policy,
+                deadline,
+            },
+            funds,
+        )
+    }
+
+    pub fn place_limit_order_msg(
+        &self,
+        offer_asset: Asset,
+        ask_asset: &AssetInfo,
+        minimum_receive: Uint128,
+        tip: Option<Uint128>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::PlaceLimitOrder {
+                offer_asset: offer_asset.into(),
+                ask_asset: ask_asset.to_owned().into(),
+                minimum_receive,
+                tip,
+            },
+            funds,
+        )
+    }
+
+    pub fn execute_limit_orders_msg(&self, order_ids: Vec<u64>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ExecuteLimitOrders { order_ids }, vec![])
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_dca_schedule_msg(
+        &self,
+        offer: &AssetInfo,
+        ask: &AssetInfo,
+        amount_per_interval: Uint128,
+        interval: u64,
+        total_budget: Uint128,
+        max_slippage: Option<Decimal>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(
+            ExecuteMsg::CreateDcaSchedule {
+                offer: offer.to_owned().into(),
+                ask: ask.to_owned().into(),
+                amount_per_interval,
+                interval,
+                total_budget,
+                max_slippage,
+            },
+            funds,
+        )
+    }
+
+    pub fn trigger_dca_msg(&self, schedule_id: u64) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::TriggerDca { schedule_id }, vec![])
+    }
+
     pub fn simulate_swap_operations(
         &self,
         querier: &QuerierWrapper,
         offer_amount: Uint128,
         operations: &SwapOperationsList,
-    ) -> StdResult<Uint128> {
+    ) -> StdResult<SimulateSwapResponse> {
         querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: self.0.to_string(),
             msg: to_json_binary(&QueryMsg::SimulateSwapOperations {
@@ -176,12 +381,27 @@ impl CwDexRouter {
         }))
     }
 
+    pub fn simulate_reverse_swap_operations(
+        &self,
+        querier: &QuerierWrapper,
+        ask_amount: Uint128,
+        operations: &SwapOperationsList,
+    ) -> StdResult<Uint128> {
+        querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: self.0.to_string(),
+            msg: to_json_binary(&QueryMsg::SimulateReverseSwapOperations {
+                ask_amount,
+                operations: operations.into(),
+            })?,
+        }))
+    }
+
     pub fn simulate_basket_liquidate(
         &self,
         querier: &QuerierWrapper,
         offer_assets: AssetList,
         receive_asset: &AssetInfo,
-    ) -> StdResult<Uint128> {
+    ) -> StdResult<SimulateSwapResponse> {
         querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: self.0.to_string(),
             msg: to_json_binary(&QueryMsg::SimulateBasketLiquidate {
@@ -196,25 +416,93 @@ impl CwDexRouter {
         querier: &QuerierWrapper,
         offer_asset: &AssetInfo,
         ask_asset: &AssetInfo,
-    ) -> StdResult<SwapOperationsList> {
+        reference_amount: Option<Uint128>,
+    ) -> StdResult<Vec<PathCandidate>> {
         querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: self.0.to_string(),
             msg: to_json_binary(&QueryMsg::PathForPair {
                 offer_asset: offer_asset.to_owned().into(),
                 ask_asset: ask_asset.to_owned().into(),
+                reference_amount,
             })?,
         }))
     }
 
+    pub fn query_best_route(
+        &self,
+        querier: &QuerierWrapper,
+        offer_asset: Asset,
+        ask_asset: &AssetInfo,
+        max_hops: Option<u32>,
+    ) -> StdResult<BestRouteResponse> {
+        querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: self.0.to_string(),
+            msg: to_json_binary(&QueryMsg::BestRoute {
+                offer_asset: offer_asset.into(),
+                ask_asset: ask_asset.to_owned().into(),
+                max_hops,
+            })?,
+        }))
+    }
+
+    pub fn query_all_paths(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<(&AssetInfo, &AssetInfo)>,
+        limit: Option<u32>,
+        filter: Option<PathFilter>,
+    ) -> StdResult<AllPathsResponse> {
+        querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: self.0.to_string(),
+            msg: to_json_binary(&QueryMsg::AllPaths {
+                start_after: start_after
+                    .map(|(offer, ask)| (offer.to_owned().into(), ask.to_owned().into())),
+                limit,
+                filter,
+            })?,
+        }))
+    }
+
+    pub fn query_path_health(
+        &self,
+        querier: &QuerierWrapper,
+        reference_amount: Uint128,
+        start_after: Option<(&AssetInfo, &AssetInfo)>,
+        limit: Option<u32>,
+        filter: Option<PathFilter>,
+    ) -> StdResult<PathHealthResponse> {
+        querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: self.0.to_string(),
+            msg: to_json_binary(&QueryMsg::PathHealth {
+                reference_amount,
+                start_after: start_after
+                    .map(|(offer, ask)| (offer.to_owned().into(), ask.to_owned().into())),
+                limit,
+                filter,
+            })?,
+        }))
+    }
+
+    pub fn query_config(&self, querier: &QuerierWrapper) -> StdResult<Config> {
+        querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: self.0.to_string(),
+            msg: to_json_binary(&QueryMsg::Config {})?,
+        }))
+    }
+
     pub fn query_supported_offer_assets(
         &self,
         querier: &QuerierWrapper,
         ask_asset: &AssetInfo,
+        start_after: Option<&AssetInfo>,
+        limit: Option<u32>,
     ) -> StdResult<Vec<AssetInfo>> {
         querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: self.0.to_string(),
             msg: to_json_binary(&QueryMsg::SupportedOfferAssets {
                 ask_asset: ask_asset.to_owned().into(),
+                start_after: start_after.map(|x| x.to_owned().into()),
+                limit,
             })?,
         }))
     }
@@ -223,11 +511,15 @@ impl CwDexRouter {
         &self,
         querier: &QuerierWrapper,
         offer_asset: &AssetInfo,
+        start_after: Option<&AssetInfo>,
+        limit: Option<u32>,
     ) -> StdResult<Vec<AssetInfo>> {
         querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: self.0.to_string(),
             msg: to_json_binary(&QueryMsg::SupportedAskAssets {
                 offer_asset: offer_asset.to_owned().into(),
+                start_after: start_after.map(|x| x.to_owned().into()),
+                limit,
             })?,
         }))
     }
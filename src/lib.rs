@@ -4,6 +4,7 @@ pub mod helpers;
 pub mod msg;
 pub mod operations;
 pub mod pool;
+pub mod routing;
 pub mod state;
 
 pub use crate::error::ContractError;
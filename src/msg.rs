@@ -1,9 +1,17 @@
-use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked, AssetListUnchecked};
+use apollo_cw_asset::{
+    AssetInfo, AssetInfoUnchecked, AssetList, AssetListUnchecked, AssetUnchecked,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{wasm_execute, Addr, CosmosMsg, Empty, Env, Uint128};
+use cosmwasm_std::{
+    wasm_execute, Addr, Binary, BlockInfo, CosmosMsg, Decimal, Empty, Env, Timestamp, Uint128,
+};
 use cw20::Cw20ReceiveMsg;
+use std::fmt;
 
-use crate::operations::{SwapOperation, SwapOperationsListUnchecked};
+use crate::operations::{
+    SplitRouteUnchecked, SwapOperation, SwapOperationsList, SwapOperationsListUnchecked,
+};
+use crate::pool::Pool;
 use crate::ContractError;
 
 pub type InstantiateMsg = Empty;
@@ -13,32 +21,345 @@ pub enum ExecuteMsg {
     Receive(Cw20ReceiveMsg),
     ExecuteSwapOperations {
         operations: SwapOperationsListUnchecked,
-        /// Optional because we only need the information if the user wants to
-        /// swap a Cw20 with TransferFrom
+        /// Amount of the offer asset to swap. Required for a Cw20 offer asset,
+        /// which is received with TransferFrom unless it was sent through
+        /// `Receive`. For a native offer asset it is taken from the coin sent,
+        /// and must match it if given.
         offer_amount: Option<Uint128>,
         minimum_receive: Option<Uint128>,
+        /// Alternative to `minimum_receive`. The operations are simulated at
+        /// execution and at least `simulated * (1 - max_slippage)` must be
+        /// received. If both are given the higher minimum is used.
+        max_slippage: Option<Decimal>,
         to: Option<String>,
+        /// If given, the swap fails once the deadline has passed. Checked
+        /// again before every hop.
+        deadline: Option<Deadline>,
+        /// If given, the output is delivered to a contract call instead of
+        /// being sent to `to`, which must then be `None`.
+        post_swap_action: Option<PostSwapAction>,
+        /// If given, the output is split between the recipients by weight
+        /// instead of being sent to `to`, which must then be `None`. Rounding
+        /// dust goes to the first recipient.
+        recipients: Option<Vec<(String, Decimal)>>,
+        /// If given, `fee_bps` of the output is sent to the referrer. Capped
+        /// by `max_referral_fee_bps` in the config.
+        referral: Option<Referral>,
+    },
+    /// Swaps the offer asset into exactly `ask_amount` of the ask asset,
+    /// spending at most `max_offer`. The needed offer amount is found by
+    /// simulating the operations in reverse and any unused offer asset is
+    /// refunded to the sender.
+    ExecuteSwapOperationsExactOut {
+        operations: SwapOperationsListUnchecked,
+        ask_amount: Uint128,
+        max_offer: Uint128,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+    },
+    /// Swaps `offer_amount` divided across the legs of `route` according to
+    /// their weights. `minimum_receive` applies to the combined output.
+    ExecuteSplitRoute {
+        route: SplitRouteUnchecked,
+        offer_amount: Uint128,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
     },
     BasketLiquidate {
         offer_assets: AssetListUnchecked,
         receive_asset: AssetInfoUnchecked,
         minimum_receive: Option<Uint128>,
+        /// Same as in `ExecuteSwapOperations`, applied to the combined
+        /// simulated output of all offer assets.
+        max_slippage: Option<Decimal>,
         to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+        /// Same as in `ExecuteMsg::ExecuteSwapOperations`.
+        recipients: Option<Vec<(String, Decimal)>>,
+        /// Same as in `ExecuteMsg::ExecuteSwapOperations`.
+        referral: Option<Referral>,
     },
     SetPath {
         offer_asset: AssetInfoUnchecked,
         ask_asset: AssetInfoUnchecked,
         path: SwapOperationsListUnchecked,
         bidirectional: bool,
+        /// If true, `path` is added to the candidate paths already stored for
+        /// the pair instead of replacing them.
+        #[serde(default)]
+        append: bool,
+        /// If given, `path`, and the reversed path if `bidirectional` is true,
+        /// must simulate to a non-zero output for this amount to be stored.
+        reference_amount: Option<Uint128>,
+    },
+    /// Removes stored paths for a pair. If `path` is given only that candidate
+    /// is removed, otherwise all candidates for the pair are.
+    RemovePath {
+        offer_asset: AssetInfoUnchecked,
+        ask_asset: AssetInfoUnchecked,
+        path: Option<SwapOperationsListUnchecked>,
+        bidirectional: bool,
+    },
+    /// Removes and sets several paths at once. Removals are applied before the
+    /// new paths are set.
+    UpdatePaths {
+        #[serde(default)]
+        remove: Vec<RemovePathMsg>,
+        #[serde(default)]
+        set: Vec<SetPathMsg>,
+    },
+    /// Disables or enables swapping between a pair with its stored paths. A
+    /// disabled pair is rejected by `BasketLiquidate` and `SwapStoredPath`,
+    /// but its paths are kept.
+    SetPathDisabled {
+        offer_asset: AssetInfoUnchecked,
+        ask_asset: AssetInfoUnchecked,
+        disabled: bool,
+    },
+    /// Updates the config. Fields that are `None` are left unchanged.
+    UpdateConfig {
+        hub_assets: Option<Vec<AssetInfoUnchecked>>,
+        max_referral_fee_bps: Option<u16>,
+    },
+    /// Sets the protocol fee taken from the output of every swap, in basis
+    /// points, and the address it is sent to. Limit orders and DCA schedules
+    /// are charged unless their owner is exempt.
+    UpdateFeeConfig {
+        fee_bps: u16,
+        fee_collector: String,
+    },
+    /// Adds and removes callers whose swaps are not charged the protocol fee.
+    UpdateFeeExemptCallers {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Swaps `offer_asset` to `ask_asset` using the stored candidate path
+    /// that is simulated to return the most `ask_asset`.
+    SwapStoredPath {
+        offer_asset: AssetUnchecked,
+        ask_asset: AssetInfoUnchecked,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+    },
+    /// Adds pools to the registry used to search for the best route between
+    /// two assets. The registry holds at most
+    /// `routing::MAX_REGISTERED_POOLS` pools.
+    RegisterPools {
+        pools: Vec<Pool>,
+    },
+    /// Removes pools from the route search registry.
+    DeregisterPools {
+        pools: Vec<Pool>,
+    },
+    /// Searches the registered pools for the route that returns the most
+    /// `ask_asset` for `offer_asset` and executes it. Fails if there are more
+    /// than `routing::MAX_CANDIDATE_ROUTES` routes within `max_hops`.
+    SwapBestRoute {
+        offer_asset: AssetUnchecked,
+        ask_asset: AssetInfoUnchecked,
+        /// Max number of swaps in the route. Defaults to
+        /// `routing::DEFAULT_MAX_HOPS` and is capped at `routing::MAX_HOPS`.
+        max_hops: Option<u32>,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+    },
+    /// Executes several independent swaps. Each swap only uses its own offer
+    /// amount and sends its output to its own recipient. With
+    /// `BatchFailurePolicy::SkipAndRefund` a failing swap is skipped and its
+    /// offer amount is refunded to the sender, otherwise the whole batch
+    /// fails.
+    BatchSwap {
+        swaps: Vec<BatchSwapEntry>,
+        #[serde(default)]
+        failure_policy: BatchFailurePolicy,
+        deadline: Option<Deadline>,
+    },
+    /// Escrows `offer_asset` and `tip` (in the offer asset) until the order
+    /// is filled or cancelled. The order is filled along the best stored path
+    /// for the pair at the time it is placed, once that path returns at least
+    /// `minimum_receive` of `ask_asset`. The output is sent to the owner.
+    PlaceLimitOrder {
+        offer_asset: AssetUnchecked,
+        ask_asset: AssetInfoUnchecked,
+        minimum_receive: Uint128,
+        tip: Option<Uint128>,
+    },
+    /// Cancels a limit order of the sender and refunds its offer asset and
+    /// tip.
+    CancelLimitOrder {
+        order_id: u64,
+    },
+    /// Fills the given limit orders whose stored path returns at least their
+    /// minimum receive. Orders that can not be filled, or no longer exist, are
+    /// skipped. Each order is filled after the ones before it, and an order
+    /// that no longer returns its minimum receive by then is left in place
+    /// without failing the others. Can be called by anyone, and the tips of
+    /// the filled orders are sent to the caller.
+    ExecuteLimitOrders {
+        order_ids: Vec<u64>,
+    },
+    /// Escrows `total_budget` of `offer` to be swapped to `ask` in slices of
+    /// `amount_per_interval`, at most one slice every `interval` seconds. The
+    /// output is sent to the owner. If `max_slippage` is given, each slice
+    /// must return at least its simulated output minus `max_slippage`.
+    CreateDcaSchedule {
+        offer: AssetInfoUnchecked,
+        ask: AssetInfoUnchecked,
+        amount_per_interval: Uint128,
+        interval: u64,
+        total_budget: Uint128,
+        max_slippage: Option<Decimal>,
+    },
+    /// Swaps the next slice of a DCA schedule along the best stored path for
+    /// its pair. Can be called by anyone once the interval since the last
+    /// slice has elapsed. The schedule is removed once its budget is spent.
+    TriggerDca {
+        schedule_id: u64,
+    },
+    /// Pauses or resumes a DCA schedule of the sender.
+    SetDcaSchedulePaused {
+        schedule_id: u64,
+        paused: bool,
+    },
+    /// Removes a DCA schedule of the sender and refunds its remaining budget.
+    WithdrawDcaSchedule {
+        schedule_id: u64,
     },
     Callback(CallbackMsg),
 }
 
+/// A swap in `ExecuteMsg::BatchSwap`. The output is sent to `to`, or the
+/// sender if not given.
 #[cw_serde]
+pub struct BatchSwapEntry {
+    pub operations: SwapOperationsListUnchecked,
+    pub offer_amount: Uint128,
+    pub minimum_receive: Option<Uint128>,
+    pub to: Option<String>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum BatchFailurePolicy {
+    /// The batch fails if any swap fails.
+    #[default]
+    AllOrNothing,
+    /// Failing swaps are skipped and their offer amount is refunded to the
+    /// sender.
+    SkipAndRefund,
+}
+
+/// A path to set in `ExecuteMsg::UpdatePaths`. Same as `ExecuteMsg::SetPath`.
+#[cw_serde]
+pub struct SetPathMsg {
+    pub offer_asset: AssetInfoUnchecked,
+    pub ask_asset: AssetInfoUnchecked,
+    pub path: SwapOperationsListUnchecked,
+    pub bidirectional: bool,
+    #[serde(default)]
+    pub append: bool,
+    pub reference_amount: Option<Uint128>,
+}
+
+/// A path to remove in `ExecuteMsg::UpdatePaths`. Same as
+/// `ExecuteMsg::RemovePath`.
+#[cw_serde]
+pub struct RemovePathMsg {
+    pub offer_asset: AssetInfoUnchecked,
+    pub ask_asset: AssetInfoUnchecked,
+    pub path: Option<SwapOperationsListUnchecked>,
+    pub bidirectional: bool,
+}
+
+#[cw_serde]
+#[allow(clippy::large_enum_variant)]
 pub enum CallbackMsg {
+    /// Swaps `offer_amount` with `operation` and continues with
+    /// `next_operations` using the amount received. The output of the last
+    /// operation is sent to `to`.
     ExecuteSwapOperation {
         operation: SwapOperation,
+        offer_amount: Uint128,
+        next_operations: Vec<SwapOperation>,
         to: Addr,
+        deadline: Option<Deadline>,
+    },
+    /// Same as `ExecuteSwapOperation`, but swaps the amount of the offer asset
+    /// the contract received since its balance was `prev_balance`.
+    ExecuteNextSwapOperation {
+        operation: SwapOperation,
+        prev_balance: Uint128,
+        next_operations: Vec<SwapOperation>,
+        to: Addr,
+        deadline: Option<Deadline>,
+    },
+    /// Sends the amount of `asset_info` the contract received since its
+    /// balance was `prev_balance` to `recipient`.
+    TransferReceived {
+        asset_info: AssetInfo,
+        prev_balance: Uint128,
+        recipient: Addr,
+    },
+    /// Splits the amount of `asset_info` the contract received since its
+    /// balance was `prev_balance` between `recipients` by weight. Rounding
+    /// dust goes to the first recipient.
+    DistributeReceived {
+        asset_info: AssetInfo,
+        prev_balance: Uint128,
+        recipients: Vec<(Addr, Decimal)>,
+    },
+    /// Executes a swap of `ExecuteMsg::BatchSwap`, or a limit order, and
+    /// asserts its minimum receive. Leftover intermediate assets are refunded
+    /// to `sender`.
+    ExecuteBatchSwapEntry {
+        operations: SwapOperationsList,
+        offer_amount: Uint128,
+        minimum_receive: Option<Uint128>,
+        recipient: Addr,
+        sender: Addr,
+        deadline: Option<Deadline>,
+    },
+    /// Fills a limit order of `ExecuteMsg::ExecuteLimitOrders` and sends its
+    /// tip to `keeper`.
+    FillLimitOrder { order_id: u64, keeper: Addr },
+    /// Removes the refunds of a `ExecuteMsg::BatchSwap` once all its swaps
+    /// are done.
+    ClearBatchSwapRefunds {},
+    /// Ends a swap once all its other messages are done, so that escrowed
+    /// assets can be moved again.
+    FinishSwap {},
+    /// Sends any increase in the contract's balances of the assets in
+    /// `prev_balances` to `recipient`.
+    RefundLeftovers {
+        prev_balances: AssetList,
+        recipient: Addr,
+    },
+    /// Delivers the amount of `asset_info` the contract received since its
+    /// balance was `prev_balance` to `contract_addr` with `msg`.
+    ExecutePostSwapAction {
+        asset_info: AssetInfo,
+        prev_balance: Uint128,
+        contract_addr: Addr,
+        msg: Binary,
+    },
+    /// Takes the protocol fee and the referral fee, given as (address, basis
+    /// points), from the amount of `asset_info` the contract received since
+    /// its balance was `prev_balance`, and sends the rest to `recipient`. The
+    /// amount received is added to the volume of the referrer.
+    CollectFee {
+        asset_info: AssetInfo,
+        prev_balance: Uint128,
+        protocol_fee: Option<(Addr, u16)>,
+        referral: Option<(Addr, u16)>,
+        recipient: Addr,
     },
     AssertMinimumReceive {
         asset_info: AssetInfo,
@@ -59,45 +380,315 @@ impl CallbackMsg {
     }
 }
 
+/// Share of the output of a swap, in basis points, sent to the integrator that
+/// referred it.
+#[cw_serde]
+pub struct Referral {
+    pub address: String,
+    pub fee_bps: u16,
+}
+
+/// Contract call made with the output of a swap. Native outputs are sent as
+/// the funds of a `WasmMsg::Execute` with `msg`, and Cw20 outputs with a
+/// `Cw20ExecuteMsg::Send` carrying `msg`. `contract_addr` may not be the
+/// router itself.
+#[cw_serde]
+pub struct PostSwapAction {
+    pub contract_addr: String,
+    pub msg: Binary,
+}
+
+/// Point after which a swap is no longer executed. A deadline has passed once
+/// the block time or height is past the given value.
+#[cw_serde]
+#[derive(Copy)]
+pub enum Deadline {
+    Timestamp(Timestamp),
+    BlockHeight(u64),
+}
+
+impl Deadline {
+    pub fn assert_not_passed(&self, block: &BlockInfo) -> Result<(), ContractError> {
+        let passed = match self {
+            Deadline::Timestamp(time) => block.time > *time,
+            Deadline::BlockHeight(height) => block.height > *height,
+        };
+        if passed {
+            return Err(ContractError::DeadlinePassed { deadline: *self });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Deadline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Deadline::Timestamp(time) => write!(f, "time {}", time),
+            Deadline::BlockHeight(height) => write!(f, "block height {}", height),
+        }
+    }
+}
+
 #[cw_serde]
 pub enum Cw20HookMsg {
+    /// Same as `ExecuteMsg::ExecuteSwapOperations` with the received Cw20 as
+    /// the offer asset. The operations must start with the received Cw20.
     ExecuteSwapOperations {
         operations: SwapOperationsListUnchecked,
         minimum_receive: Option<Uint128>,
+        /// Same as in `ExecuteMsg::ExecuteSwapOperations`.
+        max_slippage: Option<Decimal>,
+        to: Option<String>,
+        /// Same as in `ExecuteMsg::ExecuteSwapOperations`.
+        deadline: Option<Deadline>,
+        /// Same as in `ExecuteMsg::ExecuteSwapOperations`.
+        post_swap_action: Option<PostSwapAction>,
+        /// Same as in `ExecuteMsg::ExecuteSwapOperations`.
+        recipients: Option<Vec<(String, Decimal)>>,
+        /// Same as in `ExecuteMsg::ExecuteSwapOperations`.
+        referral: Option<Referral>,
+    },
+    /// Same as `ExecuteMsg::BasketLiquidate` with the received Cw20 as the
+    /// only offer asset.
+    BasketLiquidate {
+        receive_asset: AssetInfoUnchecked,
+        minimum_receive: Option<Uint128>,
+        max_slippage: Option<Decimal>,
         to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
+        recipients: Option<Vec<(String, Decimal)>>,
+        referral: Option<Referral>,
+    },
+    /// Same as `ExecuteMsg::SwapStoredPath` with the received Cw20 as the
+    /// offer asset.
+    SwapStoredPath {
+        ask_asset: AssetInfoUnchecked,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+        deadline: Option<Deadline>,
+        post_swap_action: Option<PostSwapAction>,
     },
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
-    #[returns(Uint128)]
+    /// Returns the output of the operations before and after the protocol fee.
+    /// The fee is included even if the caller is fee exempt.
+    #[returns(SimulateSwapResponse)]
     SimulateSwapOperations {
         offer_amount: Uint128,
         operations: SwapOperationsListUnchecked,
     },
 
+    /// Returns the amount of the first offer asset needed to receive at least
+    /// `ask_amount` of the last ask asset after the protocol fee. The fee is
+    /// included even if the caller is fee exempt.
     #[returns(Uint128)]
+    SimulateReverseSwapOperations {
+        ask_amount: Uint128,
+        operations: SwapOperationsListUnchecked,
+    },
+
+    /// Same as `SimulateSwapOperations` for the combined output of the legs.
+    #[returns(SimulateSwapResponse)]
+    SimulateSplitRoute {
+        offer_amount: Uint128,
+        route: SplitRouteUnchecked,
+    },
+
+    /// Finds a near-optimal split of `offer_amount` across `paths` by
+    /// iteratively simulating each path. `steps` is the number of chunks the
+    /// offer amount is divided into, defaults to
+    /// `routing::DEFAULT_SPLIT_STEPS` and is capped at
+    /// `routing::MAX_SPLIT_STEPS`.
+    #[returns(crate::routing::SplitRouteResponse)]
+    OptimizeSplitRoute {
+        offer_amount: Uint128,
+        paths: Vec<SwapOperationsListUnchecked>,
+        steps: Option<u32>,
+    },
+
+    /// Same as `SimulateSwapOperations` for `ExecuteMsg::BasketLiquidate`.
+    #[returns(SimulateSwapResponse)]
     SimulateBasketLiquidate {
         offer_assets: AssetListUnchecked,
         receive_asset: AssetInfoUnchecked,
     },
 
-    /// Returns all the current path for a given (offer_asset, ask_asset) pair.
-    #[returns(crate::operations::SwapOperationsList)]
+    /// Returns all the candidate paths for a given (offer_asset, ask_asset)
+    /// pair. If there are no stored paths for the pair, candidates are built
+    /// through the hub assets in the config. If `reference_amount` is given,
+    /// each candidate is simulated with it and the candidates are ranked by
+    /// their output, best first.
+    #[returns(Vec<PathCandidate>)]
     PathForPair {
         offer_asset: AssetInfoUnchecked,
         ask_asset: AssetInfoUnchecked,
+        reference_amount: Option<Uint128>,
     },
 
-    /// Returns all the assets from which there are paths to a given ask asset.
+    /// Returns whether swapping between the pair with its stored paths is
+    /// disabled.
+    #[returns(bool)]
+    PathDisabled {
+        offer_asset: AssetInfoUnchecked,
+        ask_asset: AssetInfoUnchecked,
+    },
+
+    /// Returns the assets from which there are paths to a given ask asset,
+    /// paginated by `start_after` and `limit`.
     #[returns(Vec<AssetInfo>)]
-    SupportedOfferAssets { ask_asset: AssetInfoUnchecked },
+    SupportedOfferAssets {
+        ask_asset: AssetInfoUnchecked,
+        start_after: Option<AssetInfoUnchecked>,
+        limit: Option<u32>,
+    },
 
-    /// Returns all the assets to which there are paths from a given offer
-    /// asset.
+    /// Returns the assets to which there are paths from a given offer asset,
+    /// paginated by `start_after` and `limit`.
     #[returns(Vec<AssetInfo>)]
-    SupportedAskAssets { offer_asset: AssetInfoUnchecked },
+    SupportedAskAssets {
+        offer_asset: AssetInfoUnchecked,
+        start_after: Option<AssetInfoUnchecked>,
+        limit: Option<u32>,
+    },
+
+    /// Returns the route through the registered pools that returns the most
+    /// `ask_asset` for `offer_asset`, along with the simulated return amount.
+    /// Fails like `ExecuteMsg::SwapBestRoute` if there are too many routes.
+    #[returns(crate::routing::BestRouteResponse)]
+    BestRoute {
+        offer_asset: AssetUnchecked,
+        ask_asset: AssetInfoUnchecked,
+        max_hops: Option<u32>,
+    },
+
+    /// Returns the stored paths of all pairs, ordered by (offer_asset,
+    /// ask_asset) and optionally filtered. `limit` is the number of pairs
+    /// returned, so that the candidates of a pair are never split across
+    /// pages. Pass `next_start_after` of a page as `start_after` to get the
+    /// next. Filtering by offer or ask asset only reads the pairs of that
+    /// asset. A page reads at most 300 pairs, so with other filters it may
+    /// hold fewer pairs than `limit`, or none, while more pairs are left.
+    #[returns(AllPathsResponse)]
+    AllPaths {
+        start_after: Option<(AssetInfoUnchecked, AssetInfoUnchecked)>,
+        limit: Option<u32>,
+        filter: Option<PathFilter>,
+    },
+
+    /// Simulates the stored paths hop by hop with `reference_amount` of the
+    /// offer asset and reports the output and estimated price impact of each
+    /// hop, or the hop that failed. Paginated and filtered like `AllPaths`.
+    #[returns(crate::routing::PathHealthResponse)]
+    PathHealth {
+        reference_amount: Uint128,
+        start_after: Option<(AssetInfoUnchecked, AssetInfoUnchecked)>,
+        limit: Option<u32>,
+        filter: Option<PathFilter>,
+    },
+
+    #[returns(crate::state::LimitOrder)]
+    LimitOrder { order_id: u64 },
+
+    /// Returns the limit orders placed by `owner`, ordered by id and paginated
+    /// by `start_after` and `limit`.
+    #[returns(Vec<crate::state::LimitOrder>)]
+    LimitOrdersByOwner {
+        owner: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(crate::state::DcaSchedule)]
+    DcaSchedule { schedule_id: u64 },
+
+    /// Returns the DCA schedules created by `owner`, ordered by id and
+    /// paginated by `start_after` and `limit`.
+    #[returns(Vec<crate::state::DcaSchedule>)]
+    DcaSchedulesByOwner {
+        owner: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(crate::state::Config)]
+    Config {},
+
+    /// Returns the protocol fee config, if set.
+    #[returns(Option<crate::state::FeeConfig>)]
+    FeeConfig {},
+
+    /// Returns the cumulative output of the swaps referred by `referrer`, per
+    /// asset, paginated by `start_after` and `limit`.
+    #[returns(Vec<apollo_cw_asset::Asset>)]
+    ReferralVolumes {
+        referrer: String,
+        start_after: Option<AssetInfoUnchecked>,
+        limit: Option<u32>,
+    },
+
+    /// Returns the callers that are not charged the protocol fee, paginated by
+    /// `start_after` and `limit`.
+    #[returns(Vec<Addr>)]
+    FeeExemptCallers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Returns all pools registered for route search.
+    #[returns(Vec<crate::routing::RegisteredPool>)]
+    RegisteredPools {},
+}
+
+/// Filter for `QueryMsg::AllPaths`. Only paths matching all of the given
+/// fields are returned.
+#[cw_serde]
+#[derive(Default)]
+pub struct PathFilter {
+    pub offer_asset: Option<AssetInfoUnchecked>,
+    pub ask_asset: Option<AssetInfoUnchecked>,
+    /// Exact number of swaps in the path.
+    pub hops: Option<u32>,
+    /// A pool that the path swaps through.
+    pub pool: Option<Pool>,
+}
+
+#[cw_serde]
+pub struct PathEntry {
+    pub offer_asset: AssetInfo,
+    pub ask_asset: AssetInfo,
+    pub path: SwapOperationsList,
+    /// Whether swapping between the pair is disabled.
+    pub disabled: bool,
+}
+
+#[cw_serde]
+pub struct AllPathsResponse {
+    pub paths: Vec<PathEntry>,
+    /// The last pair read, to be passed as `start_after` to get the next page.
+    /// `None` once all pairs have been read.
+    pub next_start_after: Option<(AssetInfo, AssetInfo)>,
+}
+
+#[cw_serde]
+pub struct SimulateSwapResponse {
+    /// Output before the protocol fee.
+    pub gross_amount: Uint128,
+    pub fee_amount: Uint128,
+    /// Output after the protocol fee.
+    pub net_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct PathCandidate {
+    pub operations: SwapOperationsList,
+    /// Amount of ask asset the path is simulated to return for the reference
+    /// amount. `None` if no reference amount was given or the simulation
+    /// failed.
+    pub simulated_output: Option<Uint128>,
 }
 
 #[cw_serde]
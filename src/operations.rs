@@ -1,15 +1,22 @@
-use crate::msg::CallbackMsg;
+use crate::msg::{CallbackMsg, Deadline};
 pub use crate::pool::Pool;
 use crate::ContractError;
 use apollo_cw_asset::{Asset, AssetInfo, AssetInfoBase};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, CosmosMsg, Deps, Env, Response, Uint128};
+use cosmwasm_std::{Addr, CosmosMsg, Decimal, Deps, Env, Fraction, Response, StdResult, Uint128};
 
 #[cw_serde]
 pub struct SwapOperationBase<T> {
     pub pool: Pool,
     pub offer_asset_info: AssetInfoBase<T>,
     pub ask_asset_info: AssetInfoBase<T>,
+    /// Minimum amount of ask asset this swap must return.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum_receive: Option<Uint128>,
+    /// Maximum price of the ask asset in units of the offer asset for this
+    /// swap. Converted to a minimum receive from the amount offered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<Decimal>,
 }
 
 impl<T> SwapOperationBase<T> {
@@ -22,6 +29,8 @@ impl<T> SwapOperationBase<T> {
             pool,
             offer_asset_info,
             ask_asset_info,
+            minimum_receive: None,
+            max_price: None,
         }
     }
 }
@@ -37,10 +46,14 @@ impl SwapOperationUnchecked {
             ask_asset_info: self.ask_asset_info.check(deps.api)?,
             offer_asset_info: self.offer_asset_info.check(deps.api)?,
             pool: self.pool.clone(),
+            minimum_receive: self.minimum_receive,
+            max_price: self.max_price,
         };
         // validate pool assets
         let pool_assets = op.pool.pool_assets(deps)?;
-        if !pool_assets.contains(&op.offer_asset_info) || !pool_assets.contains(&op.ask_asset_info)
+        if !pool_assets.contains(&op.offer_asset_info)
+            || !pool_assets.contains(&op.ask_asset_info)
+            || op.max_price.is_some_and(|price| price.is_zero())
         {
             Err(ContractError::InvalidSwapOperations {
                 operations: vec![op],
@@ -58,30 +71,91 @@ impl SwapOperation {
         env: &Env,
         offer_amount: Uint128,
         minimum_receive: Option<Uint128>,
-        recipient: Addr,
     ) -> Result<Response, ContractError> {
         let offer_asset = Asset::new(self.offer_asset_info.clone(), offer_amount);
         let minimum_receive = minimum_receive.unwrap_or(Uint128::one());
 
-        let mut response = self.pool.swap(
+        Ok(self.pool.swap(
             deps,
             env,
-            offer_asset.clone(),
+            offer_asset,
             self.ask_asset_info.clone(),
             minimum_receive,
-        )?;
-
-        if recipient != env.contract.address {
-            // Simulate swap to know how much will be returned, then add message
-            // to send tokens to recipient
-            let receive_amount =
-                self.pool
-                    .simulate_swap(deps, offer_asset, self.ask_asset_info.clone())?;
-            let receive_asset = Asset::new(self.ask_asset_info.clone(), receive_amount);
-            response = response.add_message(receive_asset.transfer_msg(recipient)?);
+        )?)
+    }
+}
+
+impl SwapOperation {
+    /// Returns the minimum amount of ask asset that swapping `offer_amount`
+    /// must return according to `minimum_receive` and `max_price`, whichever
+    /// is higher.
+    pub fn minimum_receive_for(
+        &self,
+        offer_amount: Uint128,
+    ) -> Result<Option<Uint128>, ContractError> {
+        let from_price = self
+            .max_price
+            .map(|price| {
+                offer_amount.checked_multiply_ratio(price.denominator(), price.numerator())
+            })
+            .transpose()
+            .map_err(|_| ContractError::InvalidSwapOperations {
+                operations: vec![self.clone()],
+            })?;
+        Ok(self.minimum_receive.max(from_price))
+    }
+
+    /// Simulates swapping `offer_amount` of the offer asset and returns the
+    /// amount of ask asset received.
+    pub fn simulate(&self, deps: Deps, offer_amount: Uint128) -> StdResult<Uint128> {
+        self.pool.simulate_swap(
+            deps,
+            Asset::new(self.offer_asset_info.clone(), offer_amount),
+            self.ask_asset_info.clone(),
+        )
+    }
+
+    /// Returns the smallest amount of the offer asset that is simulated to
+    /// swap into at least `ask_amount` of the ask asset. Since the pools only
+    /// support forward simulation, this searches for the amount by first
+    /// doubling an upper bound and then bisecting.
+    pub fn simulate_reverse(
+        &self,
+        deps: Deps,
+        ask_amount: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        // Amounts that fail to simulate are treated as not returning enough
+        let returns_enough = |offer_amount: Uint128| {
+            self.simulate(deps, offer_amount)
+                .is_ok_and(|amount| amount >= ask_amount)
+        };
+
+        if ask_amount.is_zero() {
+            return Ok(Uint128::zero());
         }
 
-        Ok(response)
+        let mut low = Uint128::zero();
+        let mut high = ask_amount;
+        while !returns_enough(high) {
+            low = high;
+            high = high.checked_mul(Uint128::new(2)).map_err(|_| {
+                ContractError::InsufficientLiquidity {
+                    ask_asset: self.ask_asset_info.to_string(),
+                    ask_amount,
+                }
+            })?;
+        }
+
+        while high - low > Uint128::one() {
+            let mid = low + (high - low) / Uint128::new(2);
+            if returns_enough(mid) {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+
+        Ok(high)
     }
 }
 
@@ -91,6 +165,8 @@ impl From<&SwapOperation> for SwapOperationUnchecked {
             ask_asset_info: checked.ask_asset_info.clone().into(),
             offer_asset_info: checked.offer_asset_info.clone().into(),
             pool: checked.pool.clone(),
+            minimum_receive: checked.minimum_receive,
+            max_price: checked.max_price,
         }
     }
 }
@@ -123,30 +199,45 @@ impl SwapOperationsListUnchecked {
             .map(|x| x.check(deps))
             .collect::<Result<Vec<_>, ContractError>>()?;
 
-        if operations.is_empty() {
-            return Err(ContractError::MustProvideOperations);
-        }
+        validate_operations(operations)
+    }
+}
 
-        let mut prev_ask_asset = operations.first().unwrap().ask_asset_info.clone();
-        for operation in operations.iter().skip(1) {
-            if operation.offer_asset_info != prev_ask_asset {
-                return Err(ContractError::InvalidSwapOperations { operations });
-            }
-            prev_ask_asset = operation.ask_asset_info.clone();
+/// Checks that `operations` is not empty, that each operation offers the ask
+/// asset of the previous one, that no pool is swapped through twice and that
+/// the last ask asset is not the first offer asset.
+fn validate_operations(
+    operations: Vec<SwapOperation>,
+) -> Result<SwapOperationsList, ContractError> {
+    if operations.is_empty() {
+        return Err(ContractError::MustProvideOperations);
+    }
+
+    let mut prev_ask_asset = operations.first().unwrap().ask_asset_info.clone();
+    for operation in operations.iter().skip(1) {
+        if operation.offer_asset_info != prev_ask_asset {
+            return Err(ContractError::InvalidSwapOperations { operations });
         }
+        prev_ask_asset = operation.ask_asset_info.clone();
+    }
 
-        // Check that the path never swaps through the same pool twice
-        let mut unique_pools = vec![];
-        for operation in operations.iter() {
-            if !unique_pools.contains(&operation.pool) {
-                unique_pools.push(operation.pool.clone());
-            } else {
-                return Err(ContractError::InvalidSwapOperations { operations });
-            }
+    // Check that the path never swaps through the same pool twice
+    let mut unique_pools = vec![];
+    for operation in operations.iter() {
+        if !unique_pools.contains(&operation.pool) {
+            unique_pools.push(operation.pool.clone());
+        } else {
+            return Err(ContractError::InvalidSwapOperations { operations });
         }
+    }
 
-        Ok(SwapOperationsListBase(operations))
+    // The output of a swap is measured by the contract's balance of the ask
+    // asset, which would include the offer amount if both are the same asset
+    if operations.first().unwrap().offer_asset_info == prev_ask_asset {
+        return Err(ContractError::InvalidSwapOperations { operations });
     }
+
+    Ok(SwapOperationsListBase(operations))
 }
 
 impl SwapOperationsList {
@@ -160,41 +251,62 @@ impl SwapOperationsList {
             .iter()
             .cloned()
             .map(|op| {
-                let mut op = op;
-                let tmp = op.offer_asset_info.clone();
-                op.offer_asset_info = op.ask_asset_info.clone();
-                op.ask_asset_info = tmp;
-                op
+                // Limits only apply to the original direction
+                SwapOperation::new(op.pool, op.ask_asset_info, op.offer_asset_info)
             })
             .collect::<Vec<SwapOperation>>();
         operations.reverse();
         Self::new(operations)
     }
 
-    #[allow(unused_variables, unreachable_code)]
+    /// Returns the message executing the operations in order, starting with
+    /// `offer_amount` of the first offer asset. Each following operation swaps
+    /// only the amount received from the one before it, and the output of the
+    /// last operation is sent to `recipient`.
     pub fn into_execute_msgs(
         &self,
         env: &Env,
+        offer_amount: Uint128,
         recipient: Addr,
+        deadline: Option<Deadline>,
     ) -> Result<Vec<CosmosMsg>, ContractError> {
-        let operations_len = self.0.len();
-        let mut msgs = vec![];
-        for (i, operation) in self.0.iter().enumerate() {
-            //Always send assets to self except for last operation
-            let to = if i == operations_len - 1 {
-                recipient.clone()
-            } else {
-                env.contract.address.clone()
-            };
-            msgs.push(
-                CallbackMsg::ExecuteSwapOperation {
-                    operation: operation.clone(),
-                    to,
-                }
-                .into_cosmos_msg(env)?,
-            )
+        let (operation, next_operations) = self
+            .0
+            .split_first()
+            .ok_or(ContractError::MustProvideOperations)?;
+        Ok(vec![CallbackMsg::ExecuteSwapOperation {
+            operation: operation.clone(),
+            offer_amount,
+            next_operations: next_operations.to_vec(),
+            to: recipient,
+            deadline,
         }
-        Ok(msgs)
+        .into_cosmos_msg(env)?])
+    }
+
+    /// Simulates swapping `offer_amount` through all operations in the list
+    /// and returns the amount of the last ask asset received.
+    pub fn simulate(&self, deps: Deps, offer_amount: Uint128) -> Result<Uint128, ContractError> {
+        let mut amount = offer_amount;
+        for operation in self.0.iter() {
+            amount = operation.simulate(deps, amount)?;
+        }
+        Ok(amount)
+    }
+
+    /// Walks the list backwards and returns the amount of the first offer
+    /// asset needed to receive at least `ask_amount` of the last ask asset.
+    pub fn simulate_reverse(
+        &self,
+        deps: Deps,
+        ask_amount: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        self.0
+            .iter()
+            .rev()
+            .try_fold(ask_amount, |amount, operation| {
+                operation.simulate_reverse(deps, amount)
+            })
     }
 
     pub fn from(&self) -> AssetInfo {
@@ -204,6 +316,28 @@ impl SwapOperationsList {
     pub fn to(&self) -> AssetInfo {
         self.0.last().unwrap().ask_asset_info.clone()
     }
+
+    /// Returns the operations of `self` followed by those of `other`. Fails if
+    /// the result does not pass the same rules as
+    /// `SwapOperationsListUnchecked::check`, e.g. if both lists swap through
+    /// the same pool.
+    pub fn join(&self, other: &Self) -> Result<Self, ContractError> {
+        validate_operations(self.0.iter().chain(other.0.iter()).cloned().collect())
+    }
+
+    /// Number of swaps in the list.
+    pub fn hops(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn operations(&self) -> &[SwapOperation] {
+        &self.0
+    }
+
+    /// Returns true if any of the operations swaps through `pool`.
+    pub fn contains_pool(&self, pool: &Pool) -> bool {
+        self.0.iter().any(|operation| &operation.pool == pool)
+    }
 }
 
 impl From<SwapOperationsList> for Vec<SwapOperation> {
@@ -223,12 +357,173 @@ impl From<SwapOperationsList> for SwapOperationsListUnchecked {
     }
 }
 
+#[cw_serde]
+pub struct SplitRouteLegBase<T> {
+    pub operations: SwapOperationsListBase<T>,
+    /// Share of the offer amount to swap through this leg.
+    pub weight: Decimal,
+}
+
+pub type SplitRouteLegUnchecked = SplitRouteLegBase<String>;
+
+pub type SplitRouteLeg = SplitRouteLegBase<Addr>;
+
+/// A swap that is divided across several paths between the same two assets.
+/// The weights of the legs must add up to one.
+#[cw_serde]
+pub struct SplitRouteBase<T>(Vec<SplitRouteLegBase<T>>);
+
+impl<T> IntoIterator for SplitRouteBase<T> {
+    type Item = SplitRouteLegBase<T>;
+    type IntoIter = std::vec::IntoIter<SplitRouteLegBase<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+pub type SplitRouteUnchecked = SplitRouteBase<String>;
+
+pub type SplitRoute = SplitRouteBase<Addr>;
+
+impl SplitRouteUnchecked {
+    pub fn new(legs: Vec<SplitRouteLegUnchecked>) -> Self {
+        Self(legs)
+    }
+
+    pub fn check(&self, deps: Deps) -> Result<SplitRoute, ContractError> {
+        let legs = self
+            .0
+            .iter()
+            .map(|leg| {
+                Ok(SplitRouteLeg {
+                    operations: leg.operations.check(deps)?,
+                    weight: leg.weight,
+                })
+            })
+            .collect::<Result<Vec<_>, ContractError>>()?;
+
+        let first = legs.first().ok_or(ContractError::MustProvideOperations)?;
+        let (from, to) = (first.operations.from(), first.operations.to());
+        if legs
+            .iter()
+            .any(|leg| leg.operations.from() != from || leg.operations.to() != to)
+        {
+            return Err(ContractError::InvalidSplitRoute {
+                reason: "all legs must swap between the same assets".to_string(),
+            });
+        }
+
+        if legs.iter().any(|leg| leg.weight.is_zero())
+            || legs
+                .iter()
+                .fold(Decimal::zero(), |total, leg| total + leg.weight)
+                != Decimal::one()
+        {
+            return Err(ContractError::InvalidSplitRoute {
+                reason: "weights must be non-zero and add up to one".to_string(),
+            });
+        }
+
+        Ok(SplitRouteBase(legs))
+    }
+}
+
+impl SplitRoute {
+    pub fn new(legs: Vec<SplitRouteLeg>) -> Self {
+        Self(legs)
+    }
+
+    pub fn legs(&self) -> &[SplitRouteLeg] {
+        &self.0
+    }
+
+    /// Divides `offer_amount` between the legs according to their weights.
+    /// Any remainder from rounding goes to the first leg.
+    pub fn split_amounts(&self, offer_amount: Uint128) -> Vec<Uint128> {
+        let mut amounts = self
+            .0
+            .iter()
+            .map(|leg| offer_amount * leg.weight)
+            .collect::<Vec<_>>();
+        let total = amounts.iter().fold(Uint128::zero(), |total, x| total + x);
+        amounts[0] += offer_amount - total;
+        amounts
+    }
+
+    /// Simulates each leg with its share of `offer_amount` and returns the
+    /// combined output. Each leg is simulated independently, so legs that
+    /// share a pool will return slightly less when executed.
+    pub fn simulate(&self, deps: Deps, offer_amount: Uint128) -> Result<Uint128, ContractError> {
+        self.0
+            .iter()
+            .zip(self.split_amounts(offer_amount))
+            .try_fold(Uint128::zero(), |total, (leg, amount)| {
+                Ok(total + leg.operations.simulate(deps, amount)?)
+            })
+    }
+
+    pub fn into_execute_msgs(
+        &self,
+        env: &Env,
+        offer_amount: Uint128,
+        recipient: Addr,
+        deadline: Option<Deadline>,
+    ) -> Result<Vec<CosmosMsg>, ContractError> {
+        self.0
+            .iter()
+            .zip(self.split_amounts(offer_amount))
+            .filter(|(_, amount)| !amount.is_zero())
+            .try_fold(vec![], |mut msgs, (leg, amount)| {
+                msgs.extend(leg.operations.into_execute_msgs(
+                    env,
+                    amount,
+                    recipient.clone(),
+                    deadline,
+                )?);
+                Ok(msgs)
+            })
+    }
+
+    pub fn from(&self) -> AssetInfo {
+        self.0.first().unwrap().operations.from()
+    }
+
+    pub fn to(&self) -> AssetInfo {
+        self.0.first().unwrap().operations.to()
+    }
+}
+
+impl From<&SplitRoute> for SplitRouteUnchecked {
+    fn from(checked: &SplitRoute) -> Self {
+        Self(
+            checked
+                .0
+                .iter()
+                .map(|leg| SplitRouteLegUnchecked {
+                    operations: (&leg.operations).into(),
+                    weight: leg.weight,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<SplitRoute> for SplitRouteUnchecked {
+    fn from(checked: SplitRoute) -> Self {
+        (&checked).into()
+    }
+}
+
 #[cfg(feature = "osmosis")]
 #[cfg(test)]
 mod unit_tests {
+    use std::str::FromStr;
+
     use super::Pool;
-    use crate::operations::{SwapOperation, SwapOperationsList};
+    use crate::operations::{SplitRoute, SplitRouteLeg, SwapOperation, SwapOperationsList};
     use apollo_cw_asset::AssetInfo;
+    use cosmwasm_std::{Decimal, Uint128};
     use cw_dex_osmosis::OsmosisPool;
 
     #[test]
@@ -275,6 +570,95 @@ mod unit_tests {
         )
     }
 
+    #[test]
+    fn test_minimum_receive_for() {
+        let mut op = SwapOperation::new(
+            Pool::Osmosis(OsmosisPool::unchecked(1)),
+            AssetInfo::Native("asset1".to_string()),
+            AssetInfo::Native("asset2".to_string()),
+        );
+        assert_eq!(op.minimum_receive_for(Uint128::new(1000)).unwrap(), None);
+
+        // At most 2 asset1 per asset2 means at least 500 asset2 for 1000 asset1
+        op.max_price = Some(Decimal::percent(200));
+        assert_eq!(
+            op.minimum_receive_for(Uint128::new(1000)).unwrap(),
+            Some(Uint128::new(500))
+        );
+
+        // The higher of the two limits is used
+        op.minimum_receive = Some(Uint128::new(400));
+        assert_eq!(
+            op.minimum_receive_for(Uint128::new(1000)).unwrap(),
+            Some(Uint128::new(500))
+        );
+        op.minimum_receive = Some(Uint128::new(600));
+        assert_eq!(
+            op.minimum_receive_for(Uint128::new(1000)).unwrap(),
+            Some(Uint128::new(600))
+        );
+    }
+
+    #[test]
+    fn test_join() {
+        let op = |pool_id: u64, offer: &str, ask: &str| {
+            SwapOperation::new(
+                Pool::Osmosis(OsmosisPool::unchecked(pool_id)),
+                AssetInfo::Native(offer.to_string()),
+                AssetInfo::Native(ask.to_string()),
+            )
+        };
+        let first = SwapOperationsList::new(vec![op(1, "asset1", "asset2")]);
+
+        let joined = first
+            .join(&SwapOperationsList::new(vec![op(2, "asset2", "asset3")]))
+            .unwrap();
+        assert_eq!(
+            joined,
+            SwapOperationsList::new(vec![op(1, "asset1", "asset2"), op(2, "asset2", "asset3")])
+        );
+
+        // Swapping through the same pool twice is not allowed
+        first
+            .join(&SwapOperationsList::new(vec![op(1, "asset2", "asset3")]))
+            .unwrap_err();
+
+        // The second list must start with the ask asset of the first
+        first
+            .join(&SwapOperationsList::new(vec![op(2, "asset3", "asset4")]))
+            .unwrap_err();
+
+        // The joined list may not swap back to its offer asset
+        first
+            .join(&SwapOperationsList::new(vec![op(2, "asset2", "asset1")]))
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_split_amounts() {
+        let leg = |pool_id: u64, weight: &str| SplitRouteLeg {
+            operations: SwapOperationsList::new(vec![SwapOperation::new(
+                Pool::Osmosis(OsmosisPool::unchecked(pool_id)),
+                AssetInfo::Native("asset1".to_string()),
+                AssetInfo::Native("asset2".to_string()),
+            )]),
+            weight: Decimal::from_str(weight).unwrap(),
+        };
+        let route = SplitRoute::new(vec![
+            leg(1, "0.333333333333333334"),
+            leg(2, "0.333333333333333333"),
+            leg(3, "0.333333333333333333"),
+        ]);
+
+        let amounts = route.split_amounts(Uint128::new(100));
+
+        // Rounding remainder goes to the first leg
+        assert_eq!(
+            amounts,
+            vec![Uint128::new(34), Uint128::new(33), Uint128::new(33)]
+        );
+    }
+
     #[test]
     #[cfg(feature = "osmosis")]
     #[allow(deprecated)]
@@ -0,0 +1,477 @@
+use apollo_cw_asset::AssetInfo;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Deps, Uint128};
+
+use crate::operations::{SplitRoute, SplitRouteLeg, SwapOperation, SwapOperationsList};
+use crate::pool::Pool;
+use crate::state::REGISTERED_POOLS;
+use crate::ContractError;
+
+/// Max number of hops used by the route search if none is specified.
+pub const DEFAULT_MAX_HOPS: u32 = 3;
+/// Upper bound on the number of hops the route search will consider. The
+/// number of candidate routes grows exponentially with the number of hops, so
+/// this keeps the search within the gas limit.
+pub const MAX_HOPS: u32 = 4;
+/// Upper bound on the number of pools in the registry, and on the number of
+/// candidate routes the route search enumerates and simulates. The number of
+/// routes grows combinatorially with the size of the registry, so both are
+/// capped to keep the search within the gas limit.
+pub const MAX_REGISTERED_POOLS: usize = 50;
+pub const MAX_CANDIDATE_ROUTES: usize = 100;
+/// Upper bound on the number of swaps the route search tries, including those
+/// on branches that never reach the ask asset.
+pub const MAX_SEARCH_STEPS: usize = 1_000;
+
+/// Number of chunks the offer amount is divided into when searching for the
+/// best split if none is specified.
+pub const DEFAULT_SPLIT_STEPS: u32 = 10;
+/// Upper bound on the number of chunks used when searching for the best split.
+/// Each step simulates every path once.
+pub const MAX_SPLIT_STEPS: u32 = 50;
+
+/// Price impact of a hop is estimated by comparing its rate with the rate for
+/// this fraction of the offer amount.
+pub const PRICE_IMPACT_REFERENCE_DIVISOR: u128 = 1000;
+
+/// A pool in the registry used for route search, along with the assets it
+/// contains. The assets are stored on registration so that the search does not
+/// need to query every pool.
+#[cw_serde]
+pub struct RegisteredPool {
+    pub pool: Pool,
+    pub assets: Vec<AssetInfo>,
+}
+
+impl RegisteredPool {
+    pub fn new(deps: Deps, pool: Pool) -> Result<Self, ContractError> {
+        let assets = pool.pool_assets(deps)?;
+        Ok(Self { pool, assets })
+    }
+}
+
+/// The best route found for a swap along with the amount of ask asset it is
+/// simulated to return.
+#[cw_serde]
+pub struct BestRouteResponse {
+    pub operations: SwapOperationsList,
+    pub return_amount: Uint128,
+}
+
+/// Enumerates all routes from `offer_asset` to `ask_asset` through `pools`
+/// with at most `max_hops` swaps. A route never swaps through the same pool
+/// twice and never revisits an asset. Returns an error if there are more than
+/// `MAX_CANDIDATE_ROUTES` routes, or if more than `MAX_SEARCH_STEPS` swaps are
+/// tried.
+pub fn find_routes(
+    pools: &[RegisteredPool],
+    offer_asset: &AssetInfo,
+    ask_asset: &AssetInfo,
+    max_hops: u32,
+) -> Result<Vec<SwapOperationsList>, ContractError> {
+    let mut routes = vec![];
+    let mut visited = vec![offer_asset.clone()];
+    let mut operations = vec![];
+    let mut steps = 0;
+    search(
+        pools,
+        ask_asset,
+        max_hops as usize,
+        &mut visited,
+        &mut operations,
+        &mut routes,
+        &mut steps,
+    )?;
+    Ok(routes)
+}
+
+fn search(
+    pools: &[RegisteredPool],
+    ask_asset: &AssetInfo,
+    max_hops: usize,
+    visited: &mut Vec<AssetInfo>,
+    operations: &mut Vec<SwapOperation>,
+    routes: &mut Vec<SwapOperationsList>,
+    steps: &mut usize,
+) -> Result<(), ContractError> {
+    if operations.len() >= max_hops {
+        return Ok(());
+    }
+    let current = visited.last().unwrap().clone();
+
+    for registered in pools {
+        if !registered.assets.contains(&current)
+            || operations.iter().any(|op| op.pool == registered.pool)
+        {
+            continue;
+        }
+        for next in registered.assets.iter() {
+            if visited.contains(next) {
+                continue;
+            }
+            *steps += 1;
+            if *steps > MAX_SEARCH_STEPS {
+                return Err(ContractError::RouteSearchTooLarge {
+                    max_steps: MAX_SEARCH_STEPS,
+                });
+            }
+            operations.push(SwapOperation::new(
+                registered.pool.clone(),
+                current.clone(),
+                next.clone(),
+            ));
+            if next == ask_asset {
+                if routes.len() >= MAX_CANDIDATE_ROUTES {
+                    return Err(ContractError::TooManyRoutes {
+                        max_routes: MAX_CANDIDATE_ROUTES,
+                    });
+                }
+                routes.push(SwapOperationsList::new(operations.clone()));
+            } else {
+                visited.push(next.clone());
+                search(
+                    pools, ask_asset, max_hops, visited, operations, routes, steps,
+                )?;
+                visited.pop();
+            }
+            operations.pop();
+        }
+    }
+    Ok(())
+}
+
+/// Searches the pool registry for the route from `offer_asset` to `ask_asset`
+/// that returns the most ask asset for `offer_amount`. Routes that fail to
+/// simulate are skipped.
+pub fn find_best_route(
+    deps: Deps,
+    offer_asset: &AssetInfo,
+    offer_amount: Uint128,
+    ask_asset: &AssetInfo,
+    max_hops: Option<u32>,
+) -> Result<BestRouteResponse, ContractError> {
+    let pools = REGISTERED_POOLS.may_load(deps.storage)?.unwrap_or_default();
+    let max_hops = max_hops.unwrap_or(DEFAULT_MAX_HOPS).min(MAX_HOPS);
+
+    find_routes(&pools, offer_asset, ask_asset, max_hops)?
+        .into_iter()
+        .filter_map(|operations| {
+            operations
+                .simulate(deps, offer_amount)
+                .ok()
+                .map(|return_amount| BestRouteResponse {
+                    operations,
+                    return_amount,
+                })
+        })
+        .max_by_key(|route| route.return_amount)
+        .ok_or(ContractError::NoPathFound {
+            offer: offer_asset.to_string(),
+            ask: ask_asset.to_string(),
+        })
+}
+
+/// A split route along with the amount of ask asset it is simulated to return.
+#[cw_serde]
+pub struct SplitRouteResponse {
+    pub route: SplitRoute,
+    pub return_amount: Uint128,
+}
+
+/// Finds a near-optimal division of `offer_amount` across `paths`. The offer
+/// amount is divided into `steps` chunks and each chunk is given to the path
+/// whose output increases the most from it. Since swap outputs have
+/// diminishing returns this converges on the optimal split as the number of
+/// steps increases. Paths that end up with nothing are left out of the route.
+pub fn optimize_split(
+    deps: Deps,
+    paths: Vec<SwapOperationsList>,
+    offer_amount: Uint128,
+    steps: Option<u32>,
+) -> Result<SplitRouteResponse, ContractError> {
+    if paths.is_empty() {
+        return Err(ContractError::MustProvideOperations);
+    }
+    if offer_amount.is_zero() {
+        return Err(ContractError::InvalidSplitRoute {
+            reason: "offer amount must be greater than zero".to_string(),
+        });
+    }
+
+    let steps = steps
+        .unwrap_or(DEFAULT_SPLIT_STEPS)
+        .clamp(1, MAX_SPLIT_STEPS);
+    let chunk = (offer_amount / Uint128::from(steps)).max(Uint128::one());
+
+    let mut allocated = vec![Uint128::zero(); paths.len()];
+    let mut outputs = vec![Uint128::zero(); paths.len()];
+    let mut remaining = offer_amount;
+    while !remaining.is_zero() {
+        // The last chunk also takes any remainder from the division
+        let amount = if remaining < chunk + chunk {
+            remaining
+        } else {
+            chunk
+        };
+
+        let best = paths
+            .iter()
+            .enumerate()
+            .filter_map(|(i, path)| {
+                path.simulate(deps, allocated[i] + amount)
+                    .ok()
+                    .map(|output| (i, output))
+            })
+            .max_by_key(|(i, output)| output.saturating_sub(outputs[*i]));
+        let (i, output) = best.ok_or(ContractError::InvalidSplitRoute {
+            reason: "none of the paths could be simulated".to_string(),
+        })?;
+
+        allocated[i] += amount;
+        outputs[i] = output;
+        remaining -= amount;
+    }
+
+    // Weights are rounded down, so the first leg takes whatever is left to
+    // make them add up to exactly one.
+    let mut legs = paths
+        .into_iter()
+        .zip(allocated)
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(operations, amount)| SplitRouteLeg {
+            operations,
+            weight: Decimal::from_ratio(amount, offer_amount),
+        })
+        .collect::<Vec<_>>();
+    if let Some((first, rest)) = legs.split_first_mut() {
+        first.weight = rest
+            .iter()
+            .fold(Decimal::one(), |weight, leg| weight - leg.weight);
+    }
+
+    Ok(SplitRouteResponse {
+        route: SplitRoute::new(legs),
+        return_amount: outputs.into_iter().sum(),
+    })
+}
+
+#[cw_serde]
+pub struct PathHealthResponse {
+    pub paths: Vec<PathHealth>,
+    /// Same as in `AllPathsResponse`.
+    pub next_start_after: Option<(AssetInfo, AssetInfo)>,
+}
+
+/// The result of simulating a stored path with a reference amount.
+#[cw_serde]
+pub struct PathHealth {
+    pub offer_asset: AssetInfo,
+    pub ask_asset: AssetInfo,
+    pub path: SwapOperationsList,
+    pub disabled: bool,
+    /// The simulation of each hop, up to and including the first that failed.
+    pub hops: Vec<HopHealth>,
+    /// Amount of ask asset the path returns. `None` if any hop failed.
+    pub return_amount: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct HopHealth {
+    pub operation: SwapOperation,
+    pub offer_amount: Uint128,
+    /// `None` if the simulation failed.
+    pub return_amount: Option<Uint128>,
+    /// Estimated price impact of swapping `offer_amount`. `None` if the
+    /// simulation failed or the offer amount is too small to estimate it.
+    pub price_impact: Option<Decimal>,
+    /// The error returned by the pool if the simulation failed.
+    pub error: Option<String>,
+}
+
+/// Simulates `path` hop by hop starting from `offer_amount`. Errors are
+/// recorded on the hop that failed instead of being returned, and the
+/// remaining hops are not simulated.
+pub fn simulate_hops(
+    deps: Deps,
+    path: &SwapOperationsList,
+    offer_amount: Uint128,
+) -> (Vec<HopHealth>, Option<Uint128>) {
+    let mut hops = vec![];
+    let mut amount = offer_amount;
+    for operation in path.operations() {
+        match operation.simulate(deps, amount) {
+            Ok(return_amount) => {
+                hops.push(HopHealth {
+                    operation: operation.clone(),
+                    offer_amount: amount,
+                    return_amount: Some(return_amount),
+                    price_impact: estimate_price_impact(deps, operation, amount, return_amount),
+                    error: None,
+                });
+                amount = return_amount;
+            }
+            Err(err) => {
+                hops.push(HopHealth {
+                    operation: operation.clone(),
+                    offer_amount: amount,
+                    return_amount: None,
+                    price_impact: None,
+                    error: Some(err.to_string()),
+                });
+                return (hops, None);
+            }
+        }
+    }
+    (hops, Some(amount))
+}
+
+/// Estimates the price impact of swapping `offer_amount` as how much lower
+/// its rate is than the rate for a small fraction of it.
+fn estimate_price_impact(
+    deps: Deps,
+    operation: &SwapOperation,
+    offer_amount: Uint128,
+    return_amount: Uint128,
+) -> Option<Decimal> {
+    let small_amount = offer_amount / Uint128::new(PRICE_IMPACT_REFERENCE_DIVISOR);
+    if small_amount.is_zero() {
+        return None;
+    }
+    let small_return = operation.simulate(deps, small_amount).ok()?;
+    if small_return.is_zero() {
+        return None;
+    }
+
+    let rate = Decimal::checked_from_ratio(return_amount, offer_amount).ok()?;
+    let small_rate = Decimal::checked_from_ratio(small_return, small_amount).ok()?;
+    if rate >= small_rate {
+        return Some(Decimal::zero());
+    }
+    Some(Decimal::one() - rate.checked_div(small_rate).ok()?)
+}
+
+#[cfg(feature = "osmosis")]
+#[cfg(test)]
+mod unit_tests {
+    use super::{
+        find_routes, RegisteredPool, MAX_CANDIDATE_ROUTES, MAX_HOPS, MAX_REGISTERED_POOLS,
+        MAX_SEARCH_STEPS,
+    };
+    use crate::operations::{SwapOperation, SwapOperationsList};
+    use crate::pool::Pool;
+    use crate::ContractError;
+    use apollo_cw_asset::AssetInfo;
+    use cw_dex_osmosis::OsmosisPool;
+
+    fn registered_pool(pool_id: u64, assets: &[&str]) -> RegisteredPool {
+        RegisteredPool {
+            pool: Pool::Osmosis(OsmosisPool::unchecked(pool_id)),
+            assets: assets
+                .iter()
+                .map(|denom| AssetInfo::Native(denom.to_string()))
+                .collect(),
+        }
+    }
+
+    fn op(pool_id: u64, offer: &str, ask: &str) -> SwapOperation {
+        SwapOperation::new(
+            Pool::Osmosis(OsmosisPool::unchecked(pool_id)),
+            AssetInfo::Native(offer.to_string()),
+            AssetInfo::Native(ask.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_find_routes() {
+        let pools = vec![
+            registered_pool(1, &["asset1", "asset2"]),
+            registered_pool(2, &["asset2", "asset3"]),
+            registered_pool(3, &["asset1", "asset3"]),
+            registered_pool(4, &["asset3", "asset4"]),
+        ];
+        let offer = AssetInfo::Native("asset1".to_string());
+        let ask = AssetInfo::Native("asset3".to_string());
+
+        let routes = find_routes(&pools, &offer, &ask, 3).unwrap();
+
+        assert_eq!(
+            routes,
+            vec![
+                SwapOperationsList::new(vec![op(1, "asset1", "asset2"), op(2, "asset2", "asset3")]),
+                SwapOperationsList::new(vec![op(3, "asset1", "asset3")]),
+            ]
+        );
+
+        // Only the direct route is found with a single hop
+        let routes = find_routes(&pools, &offer, &ask, 1).unwrap();
+        assert_eq!(
+            routes,
+            vec![SwapOperationsList::new(vec![op(3, "asset1", "asset3")])]
+        );
+    }
+
+    #[test]
+    fn test_find_routes_no_route() {
+        let pools = vec![
+            registered_pool(1, &["asset1", "asset2"]),
+            registered_pool(2, &["asset3", "asset4"]),
+        ];
+
+        let routes = find_routes(
+            &pools,
+            &AssetInfo::Native("asset1".to_string()),
+            &AssetInfo::Native("asset4".to_string()),
+            MAX_HOPS,
+        )
+        .unwrap();
+
+        assert!(routes.is_empty());
+    }
+
+    #[test]
+    fn test_find_routes_too_many_routes() {
+        // Every pool is a direct route between the two assets
+        let pools: Vec<_> = (0..=MAX_CANDIDATE_ROUTES as u64)
+            .map(|pool_id| registered_pool(pool_id, &["asset1", "asset2"]))
+            .collect();
+
+        let err = find_routes(
+            &pools,
+            &AssetInfo::Native("asset1".to_string()),
+            &AssetInfo::Native("asset2".to_string()),
+            1,
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            ContractError::TooManyRoutes {
+                max_routes: MAX_CANDIDATE_ROUTES
+            }
+        ));
+    }
+
+    #[test]
+    fn test_find_routes_too_many_steps() {
+        // Every pool swaps between the same assets, none of which is the ask
+        // asset, so every branch is a dead end
+        let pools: Vec<_> = (0..MAX_REGISTERED_POOLS as u64)
+            .map(|pool_id| registered_pool(pool_id, &["asset1", "asset2", "asset3", "asset4"]))
+            .collect();
+
+        let err = find_routes(
+            &pools,
+            &AssetInfo::Native("asset1".to_string()),
+            &AssetInfo::Native("asset5".to_string()),
+            MAX_HOPS,
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            ContractError::RouteSearchTooLarge {
+                max_steps: MAX_SEARCH_STEPS
+            }
+        ));
+    }
+}
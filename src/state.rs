@@ -1,11 +1,115 @@
-use apollo_cw_asset::AssetInfoKey;
+use apollo_cw_asset::{Asset, AssetInfo, AssetInfoKey};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Empty, Timestamp, Uint128};
 use cw_controllers::Admin;
-use cw_storage_plus::Map;
+use cw_storage_plus::{Item, Map};
 
 use crate::operations::SwapOperationsList;
+use crate::routing::RegisteredPool;
 
-/// As an MVP we hardcode paths for each tuple of assets (offer, ask).
-/// In a future version we want to find the path that produces the highest
-/// number of ask assets, but this will take some time to implement.
-pub const PATHS: Map<(AssetInfoKey, AssetInfoKey), SwapOperationsList> = Map::new("paths");
+/// Candidate paths for each tuple of assets (offer, ask). When swapping, every
+/// candidate is simulated and the one that returns the most ask asset is used.
+pub const PATHS: Map<(AssetInfoKey, AssetInfoKey), Vec<SwapOperationsList>> =
+    Map::new("candidate_paths");
+/// Index of the pairs in `PATHS` keyed by (ask, offer), so that the offer
+/// assets for a given ask asset can be found with a prefix scan.
+pub const PATHS_BY_ASK_ASSET: Map<(AssetInfoKey, AssetInfoKey), Empty> =
+    Map::new("paths_by_ask_asset");
+/// Single path per tuple of assets as stored by versions up to 0.5.0. Moved
+/// over to `PATHS` on migration.
+pub const LEGACY_PATHS: Map<(AssetInfoKey, AssetInfoKey), SwapOperationsList> = Map::new("paths");
+/// Tuples of assets (offer, ask) whose stored paths may not be used for
+/// swapping. The paths are kept so that the pair can be enabled again.
+pub const DISABLED_PATHS: Map<(AssetInfoKey, AssetInfoKey), Empty> = Map::new("disabled_paths");
 pub const ADMIN: Admin = Admin::new("admin");
+/// Pools that the route search in `routing` considers when looking for the
+/// best route between two assets. Stored as a single item since every search
+/// needs to load all of them anyway.
+pub const REGISTERED_POOLS: Item<Vec<RegisteredPool>> = Item::new("registered_pools");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Config {
+    /// Assets that paths are routed through when there is no stored path for
+    /// a pair. A path is then built by joining the stored paths from the offer
+    /// asset to a hub asset and from the hub asset to the ask asset.
+    pub hub_assets: Vec<AssetInfo>,
+    /// Highest referral fee that a swap may set, in basis points.
+    pub max_referral_fee_bps: u16,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+/// Offer assets of the swaps of a `BatchSwap` with
+/// `BatchFailurePolicy::SkipAndRefund`, by index of the swap. The offer asset
+/// of a failing swap is refunded to `recipient`. Only stored while the batch
+/// is executed.
+#[cw_serde]
+pub struct BatchSwapRefunds {
+    pub recipient: Addr,
+    pub offer_assets: Vec<Asset>,
+}
+
+pub const BATCH_SWAP_REFUNDS: Item<BatchSwapRefunds> = Item::new("batch_swap_refunds");
+
+/// Number of swaps being executed, including swaps started from within
+/// another swap. Escrowed assets are only moved while it is zero.
+pub const SWAPS_IN_PROGRESS: Item<u32> = Item::new("swaps_in_progress");
+
+/// A swap escrowed by the contract until it can be filled with at least
+/// `minimum_receive` of `ask_asset` along `path`. `tip` is an amount of the
+/// offer asset paid to whoever fills the order.
+#[cw_serde]
+pub struct LimitOrder {
+    pub id: u64,
+    pub owner: Addr,
+    pub offer_asset: Asset,
+    pub ask_asset: AssetInfo,
+    pub path: SwapOperationsList,
+    pub minimum_receive: Uint128,
+    pub tip: Uint128,
+}
+
+pub const LIMIT_ORDERS: Map<u64, LimitOrder> = Map::new("limit_orders");
+/// Index of `LIMIT_ORDERS` by owner.
+pub const LIMIT_ORDERS_BY_OWNER: Map<(&Addr, u64), Empty> = Map::new("limit_orders_by_owner");
+/// Id of the next limit order placed.
+pub const NEXT_LIMIT_ORDER_ID: Item<u64> = Item::new("next_limit_order_id");
+
+/// A budget of `offer_asset` held by the contract and swapped to `ask_asset`
+/// in slices of `amount_per_interval`, at most one every `interval` seconds.
+#[cw_serde]
+pub struct DcaSchedule {
+    pub id: u64,
+    pub owner: Addr,
+    pub offer_asset: AssetInfo,
+    pub ask_asset: AssetInfo,
+    pub amount_per_interval: Uint128,
+    pub interval: u64,
+    /// Part of the budget that has not been swapped yet.
+    pub remaining: Uint128,
+    pub max_slippage: Option<Decimal>,
+    /// Time the last slice was swapped, if any.
+    pub last_triggered: Option<Timestamp>,
+    pub paused: bool,
+}
+
+pub const DCA_SCHEDULES: Map<u64, DcaSchedule> = Map::new("dca_schedules");
+/// Index of `DCA_SCHEDULES` by owner.
+pub const DCA_SCHEDULES_BY_OWNER: Map<(&Addr, u64), Empty> = Map::new("dca_schedules_by_owner");
+/// Id of the next DCA schedule created.
+pub const NEXT_DCA_SCHEDULE_ID: Item<u64> = Item::new("next_dca_schedule_id");
+
+/// Protocol fee taken from the output of every swap, in basis points, and the
+/// address it is sent to.
+#[cw_serde]
+pub struct FeeConfig {
+    pub fee_bps: u16,
+    pub fee_collector: Addr,
+}
+
+pub const FEE_CONFIG: Item<FeeConfig> = Item::new("fee_config");
+/// Callers whose swaps are not charged the protocol fee.
+pub const FEE_EXEMPT_CALLERS: Map<&Addr, Empty> = Map::new("fee_exempt_callers");
+/// Cumulative output of the swaps referred by each referrer, per asset.
+pub const REFERRAL_VOLUMES: Map<(&Addr, AssetInfoKey), Uint128> = Map::new("referral_volumes");
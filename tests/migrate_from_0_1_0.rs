@@ -1,8 +1,9 @@
 #[cfg(feature = "osmosis")]
 mod tests {
-    use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked, AssetUnchecked};
+    use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked, AssetListUnchecked, AssetUnchecked};
     use cosmwasm_std::{coin, Coin, Empty, Uint128};
-    use cw_dex_router::msg::{ExecuteMsg, MigrateMsg};
+    use cw_dex_router::msg::MigrateMsg;
+    use cw_dex_router::operations::SwapOperationsListUnchecked;
     use cw_it::osmosis_std::types::cosmwasm::wasm::v1::{
         MsgMigrateContract, MsgMigrateContractResponse,
     };
@@ -10,6 +11,7 @@ mod tests {
     use cw_it::test_tube::{Account, Module, Runner, SigningAccount, Wasm};
     use cw_it::traits::CwItRunner;
     use cw_it::{Artifact, ContractType, OwnedTestRunner};
+    use serde_json::json;
 
     const TEST_ARTIFACTS_DIR: &str = "tests/test_artifacts";
 
@@ -33,6 +35,8 @@ mod tests {
                         ),
                         offer_asset_info: AssetInfo::Native(from.to_string()),
                         ask_asset_info: AssetInfo::Native(to.to_string()),
+                        minimum_receive: None,
+                        max_price: None,
                     },
                 )
                 .collect(),
@@ -102,44 +106,54 @@ mod tests {
             println!("pool: {:?}", pool);
         }
 
+        // Messages are built as JSON since the current message types have fields
+        // that the old contract does not know about.
+
         // Store two routes
         // OSMO -> ATOM
-        let execute_msg = cw_dex_router::msg::ExecuteMsg::SetPath {
-            offer_asset: AssetInfoUnchecked::Native(UOSMO.to_string()),
-            ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
-            path: osmosis_swap_operations_list_from_vec(UOSMO_UATOM_PATH).into(),
-            bidirectional: true,
-        };
+        let execute_msg = json!({
+            "set_path": {
+                "offer_asset": AssetInfoUnchecked::Native(UOSMO.to_string()),
+                "ask_asset": AssetInfoUnchecked::Native(UATOM.to_string()),
+                "path": SwapOperationsListUnchecked::from(
+                    osmosis_swap_operations_list_from_vec(UOSMO_UATOM_PATH)
+                ),
+                "bidirectional": true,
+            }
+        });
         wasm.execute(&contract_addr, &execute_msg, &[], &admin)
             .unwrap();
         // ION -> OSMO -> ATOM
-        let execute_msg = cw_dex_router::msg::ExecuteMsg::SetPath {
-            offer_asset: AssetInfoUnchecked::Native(UION.to_string()),
-            ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
-            path: osmosis_swap_operations_list_from_vec(UION_UATOM_PATH).into(),
-            bidirectional: true,
-        };
+        let execute_msg = json!({
+            "set_path": {
+                "offer_asset": AssetInfoUnchecked::Native(UION.to_string()),
+                "ask_asset": AssetInfoUnchecked::Native(UATOM.to_string()),
+                "path": SwapOperationsListUnchecked::from(
+                    osmosis_swap_operations_list_from_vec(UION_UATOM_PATH)
+                ),
+                "bidirectional": true,
+            }
+        });
         wasm.execute(&contract_addr, &execute_msg, &[], &admin)
             .unwrap();
 
         // Try basket liquidate swapping ION and OSMO to ATOM, should fail due to
         // overlapping paths bug
-        let basket_liq_msg = ExecuteMsg::BasketLiquidate {
-            offer_assets: vec![
-                AssetUnchecked::new(
-                    AssetInfoUnchecked::Native(UION.to_string()),
-                    Uint128::new(1000000),
-                ),
-                AssetUnchecked::new(
-                    AssetInfoUnchecked::Native(UOSMO.to_string()),
-                    Uint128::new(1000000),
-                ),
-            ]
-            .into(),
-            receive_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
-            minimum_receive: None,
-            to: None,
-        };
+        let basket_liq_msg = json!({
+            "basket_liquidate": {
+                "offer_assets": AssetListUnchecked::from(vec![
+                    AssetUnchecked::new(
+                        AssetInfoUnchecked::Native(UION.to_string()),
+                        Uint128::new(1000000),
+                    ),
+                    AssetUnchecked::new(
+                        AssetInfoUnchecked::Native(UOSMO.to_string()),
+                        Uint128::new(1000000),
+                    ),
+                ]),
+                "receive_asset": AssetInfoUnchecked::Native(UATOM.to_string()),
+            }
+        });
         let res = wasm
             .execute(
                 &contract_addr,
@@ -0,0 +1,210 @@
+#[cfg(feature = "osmosis")]
+mod tests {
+    use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked, AssetListUnchecked, AssetUnchecked};
+    use cosmwasm_std::{coin, Coin, Empty, Uint128};
+    use cw_dex_router::msg::{ExecuteMsg, MigrateMsg, PathCandidate, QueryMsg};
+    use cw_dex_router::operations::{SwapOperationsList, SwapOperationsListUnchecked};
+    use cw_it::osmosis_std::types::cosmos::bank::v1beta1::QueryBalanceRequest;
+    use cw_it::osmosis_std::types::cosmwasm::wasm::v1::{
+        MsgMigrateContract, MsgMigrateContractResponse,
+    };
+    use cw_it::osmosis_test_tube::{Gamm, OsmosisTestApp};
+    use cw_it::test_tube::{Account, Bank, Module, Runner, SigningAccount, Wasm};
+    use cw_it::traits::CwItRunner;
+    use cw_it::{Artifact, ContractType, OwnedTestRunner};
+    use serde_json::json;
+
+    const TEST_ARTIFACTS_DIR: &str = "tests/test_artifacts";
+    const ARTIFACTS_DIR: &str = "target/wasm32-unknown-unknown/release";
+
+    const UOSMO: &str = "uosmo";
+    const UATOM: &str = "uatom";
+    const UION: &str = "uion";
+
+    const UOSMO_UATOM_PATH: &[(u64, &str, &str); 1] = &[(1, UOSMO, UATOM)];
+    const UION_UATOM_PATH: &[(u64, &str, &str); 2] = &[(2, UION, UOSMO), (1, UOSMO, UATOM)];
+
+    fn osmosis_swap_operations_list_from_vec(vec: &[(u64, &str, &str)]) -> SwapOperationsList {
+        SwapOperationsList::new(
+            vec.iter()
+                .map(|(pool_id, from, to)| {
+                    cw_dex_router::operations::SwapOperation::new(
+                        cw_dex_router::operations::Pool::Osmosis(
+                            cw_dex_osmosis::OsmosisPool::unchecked(pool_id.to_owned()),
+                        ),
+                        AssetInfo::Native(from.to_string()),
+                        AssetInfo::Native(to.to_string()),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[allow(deprecated)]
+    fn create_basic_pool<'a>(
+        runner: &'a impl Runner<'a>,
+        pool_liquidity: Vec<Coin>,
+        signer: &SigningAccount,
+    ) {
+        Gamm::new(runner)
+            .create_basic_pool(&pool_liquidity, signer)
+            .unwrap();
+    }
+
+    #[test]
+    fn migrate_from_0_3_0() {
+        let test_app = OsmosisTestApp::new();
+        let runner = OwnedTestRunner::OsmosisTestApp(test_app);
+        let wasm = Wasm::new(&runner);
+        let admin = runner.init_default_account().unwrap();
+
+        // Upload and instantiate the old contract, which stores a single path
+        // per pair
+        let old_wasm = ContractType::Artifact(Artifact::Local(format!(
+            "{}/{}.wasm",
+            TEST_ARTIFACTS_DIR, "cw_dex_router_osmosis_0_3_0"
+        )));
+        let old_code_id = runner.store_code(old_wasm, &admin).unwrap();
+        let contract_addr = wasm
+            .instantiate(
+                old_code_id,
+                &Empty {},
+                Some(admin.address().as_str()),
+                Some("Cw Dex Router"),
+                &[],
+                &admin,
+            )
+            .unwrap()
+            .data
+            .address;
+
+        // Create pools 1 (uosmo/uatom) and 2 (uion/uosmo)
+        for (denom_a, denom_b) in [(UOSMO, UATOM), (UION, UOSMO)] {
+            create_basic_pool(
+                &runner,
+                vec![coin(1_000_000, denom_a), coin(1_000_000, denom_b)],
+                &admin,
+            );
+        }
+
+        // Store the paths with the old contract. Messages are built as JSON
+        // since the current message types have fields that the old contract
+        // does not know about.
+        for path in [UOSMO_UATOM_PATH.as_slice(), UION_UATOM_PATH.as_slice()] {
+            let path = osmosis_swap_operations_list_from_vec(path);
+            let execute_msg = json!({
+                "set_path": {
+                    "offer_asset": AssetInfoUnchecked::from(path.from()),
+                    "ask_asset": AssetInfoUnchecked::from(path.to()),
+                    "path": SwapOperationsListUnchecked::from(path),
+                    "bidirectional": true,
+                }
+            });
+            wasm.execute(&contract_addr, &execute_msg, &[], &admin)
+                .unwrap();
+        }
+
+        // Migrate to the current build
+        let new_wasm = ContractType::Artifact(Artifact::Local(format!(
+            "{}/{}.wasm",
+            ARTIFACTS_DIR, "cw_dex_router"
+        )));
+        let new_code_id = runner.store_code(new_wasm, &admin).unwrap();
+        runner
+            .execute::<_, MsgMigrateContractResponse>(
+                MsgMigrateContract {
+                    sender: admin.address(),
+                    code_id: new_code_id,
+                    msg: serde_json::to_vec(&MigrateMsg {}).unwrap(),
+                    contract: contract_addr.clone(),
+                },
+                "/cosmwasm.wasm.v1.MsgMigrateContract",
+                &admin,
+            )
+            .unwrap();
+
+        // The stored paths are moved over to the candidate paths, in both
+        // directions
+        for path in [UOSMO_UATOM_PATH.as_slice(), UION_UATOM_PATH.as_slice()] {
+            let path = osmosis_swap_operations_list_from_vec(path);
+            let reversed = path.reverse();
+            for path in [path, reversed] {
+                let candidates: Vec<PathCandidate> = wasm
+                    .query(
+                        &contract_addr,
+                        &QueryMsg::PathForPair {
+                            offer_asset: path.from().into(),
+                            ask_asset: path.to().into(),
+                            reference_amount: None,
+                        },
+                    )
+                    .unwrap();
+                assert_eq!(candidates.len(), 1);
+                assert_eq!(candidates[0].operations, path);
+            }
+        }
+
+        // The ask asset index is filled for the migrated paths
+        let offer_assets: Vec<AssetInfo> = wasm
+            .query(
+                &contract_addr,
+                &QueryMsg::SupportedOfferAssets {
+                    ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            offer_assets,
+            vec![
+                AssetInfo::Native(UION.to_string()),
+                AssetInfo::Native(UOSMO.to_string()),
+            ]
+        );
+
+        // Basket liquidate swapping ION and OSMO to ATOM through the migrated
+        // paths
+        let atom_balance = || -> Uint128 {
+            Bank::new(&runner)
+                .query_balance(&QueryBalanceRequest {
+                    address: admin.address(),
+                    denom: UATOM.to_string(),
+                })
+                .unwrap()
+                .balance
+                .unwrap()
+                .amount
+                .parse()
+                .unwrap()
+        };
+        let balance_before = atom_balance();
+        wasm.execute(
+            &contract_addr,
+            &ExecuteMsg::BasketLiquidate {
+                offer_assets: AssetListUnchecked::from(vec![
+                    AssetUnchecked::new(
+                        AssetInfoUnchecked::Native(UION.to_string()),
+                        Uint128::new(10_000),
+                    ),
+                    AssetUnchecked::new(
+                        AssetInfoUnchecked::Native(UOSMO.to_string()),
+                        Uint128::new(10_000),
+                    ),
+                ]),
+                receive_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[coin(10_000, UION), coin(10_000, UOSMO)],
+            &admin,
+        )
+        .unwrap();
+        assert!(atom_balance() > balance_before);
+    }
+}
@@ -6,25 +6,37 @@ mod osmosis_tests {
 
     use std::str::FromStr;
 
-    use cosmwasm_std::{Addr, Api};
+    use cosmwasm_std::{to_json_binary, Addr, Api, Decimal};
 
     use cosmwasm_std::{Coin, CosmosMsg};
 
     use cosmwasm_std::{QuerierWrapper, StdError, StdResult, Uint128};
 
-    use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked};
+    use apollo_cw_asset::{Asset, AssetInfo, AssetInfoUnchecked, AssetUnchecked};
+    use cw20::Cw20ReceiveMsg;
     use cw_dex_osmosis::OsmosisPool;
-    use cw_dex_router::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use cw_dex_router::msg::{
+        AllPathsResponse, BatchFailurePolicy, BatchSwapEntry, CallbackMsg, Cw20HookMsg, Deadline,
+        ExecuteMsg, InstantiateMsg, PathCandidate, PathEntry, PathFilter, PostSwapAction, QueryMsg,
+        Referral, RemovePathMsg, SetPathMsg, SimulateSwapResponse,
+    };
 
-    use cw_dex_router::operations::{SwapOperation, SwapOperationsList};
+    use cw_dex_router::operations::{
+        SplitRouteLegUnchecked, SplitRouteUnchecked, SwapOperation, SwapOperationsList,
+    };
 
     use cw_dex_router::helpers::{CwDexRouter, CwDexRouterUnchecked};
 
     use cw_dex_router::pool::Pool;
+    use cw_dex_router::routing::{
+        BestRouteResponse, PathHealth, PathHealthResponse, SplitRouteResponse,
+    };
+    use cw_dex_router::state::{DcaSchedule, LimitOrder};
     use cw_it::cosmrs::Any;
-    use cw_it::osmosis_std::types::cosmos::bank::v1beta1::QueryBalanceRequest;
+    use cw_it::osmosis_std::types::cosmos::bank::v1beta1::{MsgSend, QueryBalanceRequest};
+    use cw_it::osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
     use cw_it::osmosis_test_tube::{Gamm, OsmosisTestApp};
-    use cw_it::test_tube::{Bank, Module, RunnerResult, SigningAccount, Wasm};
+    use cw_it::test_tube::{Account, Bank, Module, RunnerResult, SigningAccount, Wasm};
     use cw_it::traits::CwItRunner;
 
     use cw_it::{self, Artifact, ContractType};
@@ -129,6 +141,8 @@ mod osmosis_tests {
                     pool: Pool::Osmosis(OsmosisPool::unchecked(pool_id.to_owned())),
                     offer_asset_info: AssetInfo::Native(from.to_string()),
                     ask_asset_info: AssetInfo::Native(to.to_string()),
+                    minimum_receive: None,
+                    max_price: None,
                 })
                 .collect(),
         )
@@ -155,6 +169,8 @@ mod osmosis_tests {
                     ask_asset,
                     path: path.into(),
                     bidirectional,
+                    append: true,
+                    reference_amount: None,
                 },
                 &[],
                 sender,
@@ -221,34 +237,2197 @@ mod osmosis_tests {
         let expected_output_path = osmosis_swap_operations_list_from_vec(output_path);
 
         // Query path for pair
-        let swap_operations: SwapOperationsList = wasm
+        let candidates: Vec<PathCandidate> = wasm
             .query(
                 &cw_dex_router_addr,
                 &QueryMsg::PathForPair {
                     offer_asset: expected_output_path.from().into(),
                     ask_asset: expected_output_path.to().into(),
+                    reference_amount: None,
                 },
             )
             .unwrap();
 
-        assert_eq!(swap_operations, expected_output_path);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].operations, expected_output_path);
 
         if bidirectional {
-            let swap_operations_reverse: SwapOperationsList = wasm
+            let candidates_reverse: Vec<PathCandidate> = wasm
                 .query(
                     &cw_dex_router_addr,
                     &QueryMsg::PathForPair {
                         offer_asset: expected_output_path.to().into(),
                         ask_asset: expected_output_path.from().into(),
+                        reference_amount: None,
                     },
                 )
                 .unwrap();
-            assert_eq!(swap_operations_reverse, expected_output_path.reverse());
+            assert_eq!(candidates_reverse.len(), 1);
+            assert_eq!(
+                candidates_reverse[0].operations,
+                expected_output_path.reverse()
+            );
         }
 
         Ok(())
     }
 
+    #[test_case(None ; "no reference amount")]
+    #[test_case(Some(1000) ; "reference amount simulates in both directions")]
+    #[test_case(Some(1) => matches Err(_) ; "reference amount too small")]
+    fn test_set_path_with_reference_amount(reference_amount: Option<u128>) -> RunnerResult<()> {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id)?;
+
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetPath {
+                offer_asset: AssetInfoUnchecked::Native(UOSMO.to_string()),
+                ask_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                path: osmosis_swap_operations_list_from_vec(UOSMO_UATOM_UION_PATH).into(),
+                bidirectional: true,
+                append: false,
+                reference_amount: reference_amount.map(Uint128::new),
+            },
+            &[],
+            admin,
+        )?;
+
+        // Both directions are stored
+        for (offer_asset, ask_asset) in [(UOSMO, UION), (UION, UOSMO)] {
+            let candidates: Vec<PathCandidate> = wasm.query(
+                &cw_dex_router_addr,
+                &QueryMsg::PathForPair {
+                    offer_asset: AssetInfoUnchecked::Native(offer_asset.to_string()),
+                    ask_asset: AssetInfoUnchecked::Native(ask_asset.to_string()),
+                    reference_amount: None,
+                },
+            )?;
+            assert_eq!(candidates.len(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disable_and_remove_path() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let uosmo = AssetInfoUnchecked::Native(UOSMO.to_string());
+        let uatom = AssetInfoUnchecked::Native(UATOM.to_string());
+        let uion = AssetInfoUnchecked::Native(UION.to_string());
+        let basket_liquidate = |sender: &SigningAccount| {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::BasketLiquidate {
+                    offer_assets: vec![AssetUnchecked::new(uosmo.clone(), Uint128::new(1000))]
+                        .into(),
+                    receive_asset: uatom.clone(),
+                    minimum_receive: None,
+                    max_slippage: None,
+                    to: None,
+                    deadline: None,
+                    post_swap_action: None,
+                    recipients: None,
+                    referral: None,
+                },
+                &[Coin::new(1000, UOSMO)],
+                sender,
+            )
+        };
+        let query_path_for_pair =
+            |offer_asset: &AssetInfoUnchecked, ask_asset: &AssetInfoUnchecked| {
+                wasm.query::<_, Vec<PathCandidate>>(
+                    &cw_dex_router_addr,
+                    &QueryMsg::PathForPair {
+                        offer_asset: offer_asset.clone(),
+                        ask_asset: ask_asset.clone(),
+                        reference_amount: None,
+                    },
+                )
+            };
+
+        // Set paths in both directions in one batch
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::UpdatePaths {
+                remove: vec![],
+                set: vec![
+                    SetPathMsg {
+                        offer_asset: uosmo.clone(),
+                        ask_asset: uatom.clone(),
+                        path: osmosis_swap_operations_list_from_vec(UOSMO_UATOM_PATH).into(),
+                        bidirectional: true,
+                        append: false,
+                        reference_amount: None,
+                    },
+                    SetPathMsg {
+                        offer_asset: uosmo.clone(),
+                        ask_asset: uion.clone(),
+                        path: osmosis_swap_operations_list_from_vec(UOSMO_UATOM_UION_PATH).into(),
+                        bidirectional: false,
+                        append: false,
+                        reference_amount: None,
+                    },
+                ],
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+        basket_liquidate(admin).unwrap();
+
+        // Only the admin can disable a path
+        let disable_msg = ExecuteMsg::SetPathDisabled {
+            offer_asset: uosmo.clone(),
+            ask_asset: uatom.clone(),
+            disabled: true,
+        };
+        wasm.execute(&cw_dex_router_addr, &disable_msg, &[], &accs[1])
+            .unwrap_err();
+        wasm.execute(&cw_dex_router_addr, &disable_msg, &[], admin)
+            .unwrap();
+
+        // A disabled pair is rejected but its path is kept
+        let err = basket_liquidate(admin).unwrap_err();
+        assert!(err.to_string().contains("is disabled"));
+        assert_eq!(query_path_for_pair(&uosmo, &uatom).unwrap().len(), 1);
+        let disabled: bool = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::PathDisabled {
+                    offer_asset: uosmo.clone(),
+                    ask_asset: uatom.clone(),
+                },
+            )
+            .unwrap();
+        assert!(disabled);
+
+        // Enabling the pair again allows swapping
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetPathDisabled {
+                offer_asset: uosmo.clone(),
+                ask_asset: uatom.clone(),
+                disabled: false,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+        basket_liquidate(admin).unwrap();
+
+        // Remove the path in both directions
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::RemovePath {
+                offer_asset: uosmo.clone(),
+                ask_asset: uatom.clone(),
+                path: None,
+                bidirectional: true,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+        query_path_for_pair(&uosmo, &uatom).unwrap_err();
+        query_path_for_pair(&uatom, &uosmo).unwrap_err();
+        query_path_for_pair(&uosmo, &uion).unwrap();
+
+        // Removing a path that is not stored fails
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::UpdatePaths {
+                remove: vec![RemovePathMsg {
+                    offer_asset: uosmo.clone(),
+                    ask_asset: uatom.clone(),
+                    path: None,
+                    bidirectional: false,
+                }],
+                set: vec![],
+            },
+            &[],
+            admin,
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_paths_composed_through_hub_assets() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // Store uosmo -> uatom and uatom -> uion, but nothing for uosmo -> uion
+        set_paths(
+            &app,
+            &cw_dex_router_addr,
+            &[
+                ((UOSMO, UATOM), UOSMO_UATOM_PATH),
+                ((UATOM, UION), &[(2, UATOM, UION)]),
+            ],
+            admin,
+            false,
+        )
+        .unwrap();
+        let query_path_for_pair = || {
+            wasm.query::<_, Vec<PathCandidate>>(
+                &cw_dex_router_addr,
+                &QueryMsg::PathForPair {
+                    offer_asset: AssetInfoUnchecked::Native(UOSMO.to_string()),
+                    ask_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                    reference_amount: None,
+                },
+            )
+        };
+        query_path_for_pair().unwrap_err();
+
+        // Only the admin can update the config
+        let update_config_msg = ExecuteMsg::UpdateConfig {
+            hub_assets: Some(vec![AssetInfoUnchecked::Native(UATOM.to_string())]),
+            max_referral_fee_bps: None,
+        };
+        wasm.execute(&cw_dex_router_addr, &update_config_msg, &[], &accs[1])
+            .unwrap_err();
+        wasm.execute(&cw_dex_router_addr, &update_config_msg, &[], admin)
+            .unwrap();
+
+        // The path is joined through uatom
+        let candidates = query_path_for_pair().unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].operations,
+            osmosis_swap_operations_list_from_vec(UOSMO_UATOM_UION_PATH)
+        );
+
+        // Basket liquidate uses the joined path
+        let balance_before = bank_balance_query(&app, admin.address(), UION.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::BasketLiquidate {
+                offer_assets: vec![AssetUnchecked::new(
+                    AssetInfoUnchecked::Native(UOSMO.to_string()),
+                    Uint128::new(1000),
+                )]
+                .into(),
+                receive_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(1000, UOSMO)],
+            admin,
+        )
+        .unwrap();
+        let balance_after = bank_balance_query(&app, admin.address(), UION.to_string()).unwrap();
+        assert!(balance_after > balance_before);
+    }
+
+    #[test]
+    fn test_candidate_paths_ranked_by_simulated_output() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // Pools 1 and 3 are both uosmo/uatom pools. Store a path through each.
+        set_paths(
+            &app,
+            &cw_dex_router_addr,
+            &[
+                ((UOSMO, UATOM), &[(1, UOSMO, UATOM)]),
+                ((UOSMO, UATOM), &[(3, UOSMO, UATOM)]),
+            ],
+            admin,
+            false,
+        )
+        .unwrap();
+
+        // Drain pool 1 a bit so that the path through pool 3 is better
+        let path_through_pool_1 = osmosis_swap_operations_list_from_vec(&[(1, UOSMO, UATOM)]);
+        let path_through_pool_3 = osmosis_swap_operations_list_from_vec(&[(3, UOSMO, UATOM)]);
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: path_through_pool_1.clone().into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(100_000, UOSMO)],
+            &accs[1],
+        )
+        .unwrap();
+
+        let candidates: Vec<PathCandidate> = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::PathForPair {
+                    offer_asset: AssetInfoUnchecked::Native(UOSMO.to_string()),
+                    ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                    reference_amount: Some(Uint128::new(1000)),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].operations, path_through_pool_3);
+        assert_eq!(candidates[1].operations, path_through_pool_1);
+        assert!(candidates[0].simulated_output > candidates[1].simulated_output);
+    }
+
+    #[test_case(Decimal::percent(1), true ; "within slippage")]
+    #[test_case(Decimal::zero(), true ; "zero slippage")]
+    #[test_case(Decimal::percent(101), false ; "slippage greater than one")]
+    fn test_execute_swap_operations_with_max_slippage(max_slippage: Decimal, succeeds: bool) {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let operations = osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]);
+        let offer_amount = Uint128::new(10_000);
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap()
+            .net_amount;
+
+        let balance_before = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+        let res = wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: operations.into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: Some(max_slippage),
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        );
+        let balance_after = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+
+        assert_eq!(res.is_ok(), succeeds);
+        if succeeds {
+            assert_eq!(balance_after - balance_before, simulated);
+        }
+    }
+
+    #[test_case(None, None, true ; "no hop limits")]
+    #[test_case(Some(0), None, true ; "hop minimum met")]
+    #[test_case(Some(1), None, false ; "hop minimum not met")]
+    #[test_case(None, Some("1.5"), true ; "hop max price met")]
+    #[test_case(None, Some("1"), false ; "hop max price not met")]
+    fn test_execute_swap_operations_with_hop_limits(
+        minimum_receive_offset: Option<u128>,
+        max_price: Option<&str>,
+        succeeds: bool,
+    ) {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let mut operations: Vec<SwapOperation> =
+            osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM), (1, UATOM, UOSMO)]).into();
+        let offer_amount = Uint128::new(10_000);
+        let first_hop_output = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: SwapOperationsList::new(vec![operations[0].clone()]).into(),
+                },
+            )
+            .unwrap()
+            .gross_amount;
+
+        // The minimum of the first hop is offset from its simulated output
+        operations[0].minimum_receive =
+            minimum_receive_offset.map(|offset| first_hop_output + Uint128::new(offset));
+        operations[0].max_price = max_price.map(|price| Decimal::from_str(price).unwrap());
+
+        let res = wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: SwapOperationsList::new(operations).into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        );
+        assert_eq!(res.is_ok(), succeeds);
+    }
+
+    #[test]
+    fn test_execute_swap_operations_leaves_router_balances() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // Tokens held by the router, of both the offer and the intermediate asset
+        let stray_amount = Uint128::new(5_000);
+        Bank::new(&app)
+            .send(
+                MsgSend {
+                    from_address: admin.address(),
+                    to_address: cw_dex_router_addr.clone(),
+                    amount: vec![
+                        ProtoCoin {
+                            denom: UATOM.to_string(),
+                            amount: stray_amount.to_string(),
+                        },
+                        ProtoCoin {
+                            denom: UION.to_string(),
+                            amount: stray_amount.to_string(),
+                        },
+                    ],
+                },
+                admin,
+            )
+            .unwrap();
+
+        let operations =
+            osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM), (1, UATOM, UOSMO)]);
+        let offer_amount = Uint128::new(10_000);
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap()
+            .net_amount;
+
+        let balance_before = bank_balance_query(&app, sender.address(), UOSMO.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: operations.into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let balance_after = bank_balance_query(&app, sender.address(), UOSMO.to_string()).unwrap();
+
+        // Only the sent amount was swapped at each hop
+        assert_eq!(balance_after - balance_before, simulated);
+        for denom in [UATOM, UION] {
+            let router_balance =
+                bank_balance_query(&app, cw_dex_router_addr.clone(), denom.to_string()).unwrap();
+            assert_eq!(router_balance, stray_amount);
+        }
+    }
+
+    #[test]
+    fn test_execute_swap_operations_transfers_received_amount() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let recipient = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let operations =
+            osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM), (1, UATOM, UOSMO)]);
+        let offer_amount = Uint128::new(10_000);
+
+        let balance_before =
+            bank_balance_query(&app, recipient.address(), UOSMO.to_string()).unwrap();
+        let res = wasm
+            .execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteSwapOperations {
+                    operations: operations.into(),
+                    offer_amount: None,
+                    minimum_receive: None,
+                    max_slippage: None,
+                    to: Some(recipient.address()),
+                    deadline: None,
+                    post_swap_action: None,
+                    recipients: None,
+                    referral: None,
+                },
+                &[Coin::new(offer_amount.u128(), UION)],
+                sender,
+            )
+            .unwrap();
+        let balance_after =
+            bank_balance_query(&app, recipient.address(), UOSMO.to_string()).unwrap();
+
+        // The amount sent to the recipient is the amount the last swap returned
+        let transferred = res
+            .events
+            .iter()
+            .find(|event| event.ty == "wasm-apollo/cw-dex-router/callback_transfer_received")
+            .and_then(|event| event.attributes.iter().find(|attr| attr.key == "asset"))
+            .map(|attr| attr.value.clone())
+            .unwrap();
+        assert_eq!(
+            transferred,
+            Asset::new(AssetInfo::native(UOSMO), balance_after - balance_before).to_string()
+        );
+        let router_balance =
+            bank_balance_query(&app, cw_dex_router_addr.clone(), UOSMO.to_string()).unwrap();
+        assert!(router_balance.is_zero());
+    }
+
+    #[test]
+    fn test_execute_swap_operations_with_post_swap_action() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let recipient = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+        // A second router is used as the contract called with the output
+        let second_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let first_path = osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]);
+        let second_path = osmosis_swap_operations_list_from_vec(&[(1, UATOM, UOSMO)]);
+        let offer_amount = Uint128::new(10_000);
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: osmosis_swap_operations_list_from_vec(&[
+                        (2, UION, UATOM),
+                        (1, UATOM, UOSMO),
+                    ])
+                    .into(),
+                },
+            )
+            .unwrap()
+            .net_amount;
+
+        // The output of the first swap is sent as funds to a second swap on the
+        // second router, which sends its output to the recipient
+        let post_swap_action = PostSwapAction {
+            contract_addr: second_router_addr,
+            msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperations {
+                operations: second_path.into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: Some(recipient.address()),
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            })
+            .unwrap(),
+        };
+
+        let balance_before =
+            bank_balance_query(&app, recipient.address(), UOSMO.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: first_path.clone().into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: Some(post_swap_action.clone()),
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let balance_after =
+            bank_balance_query(&app, recipient.address(), UOSMO.to_string()).unwrap();
+        assert_eq!(balance_after - balance_before, simulated);
+
+        // The post swap action is skipped if nothing is left of the output,
+        // here because the protocol fee takes all of it
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::UpdateFeeConfig {
+                fee_bps: 10_000,
+                fee_collector: admin.address(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+        let res = wasm
+            .execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteSwapOperations {
+                    operations: first_path.clone().into(),
+                    offer_amount: None,
+                    minimum_receive: None,
+                    max_slippage: None,
+                    to: None,
+                    deadline: None,
+                    post_swap_action: Some(post_swap_action.clone()),
+                    recipients: None,
+                    referral: None,
+                },
+                &[Coin::new(offer_amount.u128(), UION)],
+                sender,
+            )
+            .unwrap();
+        assert!(
+            !res.events
+                .iter()
+                .any(|event| event.ty
+                    == "wasm-apollo/cw-dex-router/callback_execute_post_swap_action")
+        );
+        assert_eq!(
+            bank_balance_query(&app, recipient.address(), UOSMO.to_string()).unwrap(),
+            balance_after
+        );
+
+        // A recipient can't be given together with a post swap action
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: first_path.into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: Some(recipient.address()),
+                deadline: None,
+                post_swap_action: Some(post_swap_action),
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_swap_output_can_not_be_sent_to_router() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let attacker = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+        let second_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // Tokens held by the router, e.g. the escrow of limit orders
+        let router_amount = Uint128::new(5_000);
+        Bank::new(&app)
+            .send(
+                MsgSend {
+                    from_address: admin.address(),
+                    to_address: cw_dex_router_addr.clone(),
+                    amount: vec![ProtoCoin {
+                        denom: UOSMO.to_string(),
+                        amount: router_amount.to_string(),
+                    }],
+                },
+                admin,
+            )
+            .unwrap();
+
+        // A callback sending the router's whole balance to the attacker
+        let forged_callback =
+            to_json_binary(&ExecuteMsg::Callback(CallbackMsg::TransferReceived {
+                asset_info: AssetInfo::Native(UOSMO.to_string()),
+                prev_balance: Uint128::zero(),
+                recipient: Addr::unchecked(attacker.address()),
+            }))
+            .unwrap();
+        let swap = |post_swap_action: Option<PostSwapAction>,
+                    to: Option<String>,
+                    recipients: Option<Vec<(String, Decimal)>>| {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteSwapOperations {
+                    operations: osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]).into(),
+                    offer_amount: None,
+                    minimum_receive: None,
+                    max_slippage: None,
+                    to,
+                    deadline: None,
+                    post_swap_action,
+                    recipients,
+                    referral: None,
+                },
+                &[Coin::new(10_000, UION)],
+                attacker,
+            )
+        };
+
+        // The router can not be the post swap action contract, so the callback
+        // can not be sent by the router itself
+        let err = swap(
+            Some(PostSwapAction {
+                contract_addr: cw_dex_router_addr.clone(),
+                msg: forged_callback.clone(),
+            }),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can not be the recipient or the post swap action contract"));
+
+        // Sent through another contract, the callback is unauthorized
+        let err = swap(
+            Some(PostSwapAction {
+                contract_addr: second_router_addr,
+                msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperations {
+                    operations: osmosis_swap_operations_list_from_vec(&[(1, UATOM, UOSMO)]).into(),
+                    offer_amount: None,
+                    minimum_receive: None,
+                    max_slippage: None,
+                    to: None,
+                    deadline: None,
+                    post_swap_action: Some(PostSwapAction {
+                        contract_addr: cw_dex_router_addr.clone(),
+                        msg: forged_callback,
+                    }),
+                    recipients: None,
+                    referral: None,
+                })
+                .unwrap(),
+            }),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Unauthorized"));
+
+        // The router can not be given as `to` or as a recipient either
+        for (to, recipients) in [
+            (Some(cw_dex_router_addr.clone()), None),
+            (
+                None,
+                Some(vec![
+                    (attacker.address(), Decimal::percent(50)),
+                    (cw_dex_router_addr.clone(), Decimal::percent(50)),
+                ]),
+            ),
+        ] {
+            let err = swap(None, to, recipients).unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("can not be the recipient or the post swap action contract"));
+        }
+
+        let router_balance =
+            bank_balance_query(&app, cw_dex_router_addr, UOSMO.to_string()).unwrap();
+        assert_eq!(router_balance, router_amount);
+    }
+
+    #[test_case(&[(10_000, UION)], None, true ; "offer asset sent")]
+    #[test_case(&[(10_000, UION)], Some(10_000), true ; "offer amount matches sent")]
+    #[test_case(&[(10_000, UION)], Some(9_000), false ; "offer amount does not match sent")]
+    #[test_case(&[(10_000, UOSMO)], None, false ; "wrong denom sent")]
+    #[test_case(&[(10_000, UION), (10_000, UOSMO)], None, false ; "extra denom sent")]
+    #[test_case(&[], None, false ; "nothing sent")]
+    fn test_execute_swap_operations_funds_validation(
+        funds: &[(u128, &str)],
+        offer_amount: Option<u128>,
+        succeeds: bool,
+    ) {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let funds: Vec<Coin> = funds
+            .iter()
+            .map(|(amount, denom)| Coin::new(*amount, *denom))
+            .collect();
+        let res = wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]).into(),
+                offer_amount: offer_amount.map(Uint128::new),
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &funds,
+            sender,
+        );
+        assert_eq!(res.is_ok(), succeeds);
+    }
+
+    #[test]
+    fn test_receive_cw20_operations_must_start_with_received_cw20() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let fake_cw20 = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // The caller claims to have sent a Cw20, but the operations swap a
+        // native asset out of the router
+        let hook = Cw20HookMsg::ExecuteSwapOperations {
+            operations: osmosis_swap_operations_list_from_vec(&[(1, UOSMO, UATOM)]).into(),
+            minimum_receive: None,
+            max_slippage: None,
+            to: None,
+            deadline: None,
+            post_swap_action: None,
+            recipients: None,
+            referral: None,
+        };
+        let err = wasm
+            .execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::Receive(Cw20ReceiveMsg {
+                    sender: fake_cw20.address(),
+                    amount: Uint128::new(10_000),
+                    msg: to_json_binary(&hook).unwrap(),
+                }),
+                &[],
+                fake_cw20,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid swap operations"));
+    }
+
+    #[test]
+    fn test_execute_swap_operations_with_recipients() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let recipients = &accs[2..5];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let operations = osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]);
+        let offer_amount = Uint128::new(10_000);
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap()
+            .net_amount;
+
+        let balances_before: Vec<Uint128> = recipients
+            .iter()
+            .map(|acc| bank_balance_query(&app, acc.address(), UATOM.to_string()).unwrap())
+            .collect();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: operations.into(),
+                offer_amount: None,
+                // The minimum applies to the total output of all recipients
+                minimum_receive: Some(simulated),
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: Some(
+                    recipients
+                        .iter()
+                        .map(|acc| (acc.address(), Decimal::one()))
+                        .collect(),
+                ),
+                referral: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let received: Vec<Uint128> = recipients
+            .iter()
+            .zip(balances_before)
+            .map(|(acc, before)| {
+                bank_balance_query(&app, acc.address(), UATOM.to_string()).unwrap() - before
+            })
+            .collect();
+
+        // Equal shares rounded down, with the dust going to the first recipient
+        let share = simulated.multiply_ratio(1u128, 3u128);
+        assert_eq!(received[1], share);
+        assert_eq!(received[2], share);
+        assert_eq!(received[0], simulated - share - share);
+    }
+
+    #[test_case(BatchFailurePolicy::AllOrNothing ; "all or nothing")]
+    #[test_case(BatchFailurePolicy::SkipAndRefund ; "skip and refund")]
+    fn test_batch_swap(failure_policy: BatchFailurePolicy) {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let recipients = &accs[2..4];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // Both swaps offer uion, the second one can not receive its minimum
+        let operations = osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]);
+        let offer_amounts = [Uint128::new(10_000), Uint128::new(20_000)];
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount: offer_amounts[0],
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap()
+            .net_amount;
+        let swaps = vec![
+            BatchSwapEntry {
+                operations: operations.clone().into(),
+                offer_amount: offer_amounts[0],
+                minimum_receive: Some(simulated),
+                to: Some(recipients[0].address()),
+            },
+            BatchSwapEntry {
+                operations: operations.into(),
+                offer_amount: offer_amounts[1],
+                minimum_receive: Some(Uint128::MAX),
+                to: Some(recipients[1].address()),
+            },
+        ];
+
+        let uion_before = bank_balance_query(&app, sender.address(), UION.to_string()).unwrap();
+        let uatom_before: Vec<Uint128> = recipients
+            .iter()
+            .map(|acc| bank_balance_query(&app, acc.address(), UATOM.to_string()).unwrap())
+            .collect();
+        let res = wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::BatchSwap {
+                swaps,
+                failure_policy: failure_policy.clone(),
+                deadline: None,
+            },
+            &[Coin::new(
+                (offer_amounts[0] + offer_amounts[1]).u128(),
+                UION,
+            )],
+            sender,
+        );
+        let uion_spent =
+            uion_before - bank_balance_query(&app, sender.address(), UION.to_string()).unwrap();
+        let received: Vec<Uint128> = recipients
+            .iter()
+            .zip(uatom_before)
+            .map(|(acc, before)| {
+                bank_balance_query(&app, acc.address(), UATOM.to_string()).unwrap() - before
+            })
+            .collect();
+
+        match failure_policy {
+            BatchFailurePolicy::AllOrNothing => {
+                assert!(res.is_err());
+                assert_eq!(uion_spent, Uint128::zero());
+                assert_eq!(received[0], Uint128::zero());
+            }
+            BatchFailurePolicy::SkipAndRefund => {
+                // The failed swap is refunded and the other one is unaffected
+                res.unwrap();
+                assert_eq!(uion_spent, offer_amounts[0]);
+                assert_eq!(received[0], simulated);
+            }
+        }
+        assert_eq!(received[1], Uint128::zero());
+
+        // Nothing is left in the router
+        let router_uion =
+            bank_balance_query(&app, cw_dex_router_addr.clone(), UION.to_string()).unwrap();
+        assert_eq!(router_uion, Uint128::zero());
+    }
+
+    #[test]
+    fn test_limit_orders() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let owner = &accs[1];
+        let keeper = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let operations = osmosis_swap_operations_list_from_vec(UION_UATOM_PATH);
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetPath {
+                offer_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                path: operations.clone().into(),
+                bidirectional: false,
+                append: false,
+                reference_amount: None,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let offer_amount = Uint128::new(10_000);
+        let tip = Uint128::new(100);
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.into(),
+                },
+            )
+            .unwrap()
+            .net_amount;
+
+        // Place an order that can not be filled yet, and one that can
+        for minimum_receive in [simulated * Uint128::new(2), simulated] {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::PlaceLimitOrder {
+                    offer_asset: AssetUnchecked::new(
+                        AssetInfoUnchecked::Native(UION.to_string()),
+                        offer_amount,
+                    ),
+                    ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                    minimum_receive,
+                    tip: Some(tip),
+                },
+                &[Coin::new((offer_amount + tip).u128(), UION)],
+                owner,
+            )
+            .unwrap();
+        }
+        let orders: Vec<LimitOrder> = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::LimitOrdersByOwner {
+                    owner: owner.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(orders.iter().map(|x| x.id).collect::<Vec<_>>(), vec![0, 1]);
+
+        // Only the second order is filled, and the keeper receives its tip
+        let err = wasm
+            .execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteLimitOrders { order_ids: vec![0] },
+                &[],
+                keeper,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("None of the limit orders can be filled"));
+        let uatom_before = bank_balance_query(&app, owner.address(), UATOM.to_string()).unwrap();
+        let uion_before = bank_balance_query(&app, keeper.address(), UION.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteLimitOrders {
+                order_ids: vec![0, 1],
+            },
+            &[],
+            keeper,
+        )
+        .unwrap();
+        let uatom_after = bank_balance_query(&app, owner.address(), UATOM.to_string()).unwrap();
+        let uion_after = bank_balance_query(&app, keeper.address(), UION.to_string()).unwrap();
+        assert_eq!(uatom_after - uatom_before, simulated);
+        assert_eq!(uion_after - uion_before, tip);
+        let orders: Vec<LimitOrder> = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::LimitOrdersByOwner {
+                    owner: owner.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(orders.iter().map(|x| x.id).collect::<Vec<_>>(), vec![0]);
+
+        // Only the owner can cancel the remaining order, which refunds the offer
+        // amount and the tip
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::CancelLimitOrder { order_id: 0 },
+            &[],
+            keeper,
+        )
+        .unwrap_err();
+        let uion_before = bank_balance_query(&app, owner.address(), UION.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::CancelLimitOrder { order_id: 0 },
+            &[],
+            owner,
+        )
+        .unwrap();
+        let uion_after = bank_balance_query(&app, owner.address(), UION.to_string()).unwrap();
+        assert_eq!(uion_after - uion_before, offer_amount + tip);
+        let router_uion = bank_balance_query(&app, cw_dex_router_addr, UION.to_string()).unwrap();
+        assert_eq!(router_uion, Uint128::zero());
+    }
+
+    #[test]
+    fn test_limit_orders_on_the_same_pool() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let owner = &accs[1];
+        let keeper = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let operations = osmosis_swap_operations_list_from_vec(UION_UATOM_PATH);
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetPath {
+                offer_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                path: operations.clone().into(),
+                bidirectional: false,
+                append: false,
+                reference_amount: None,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let offer_amount = Uint128::new(10_000);
+        let tip = Uint128::new(100);
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.into(),
+                },
+            )
+            .unwrap()
+            .net_amount;
+
+        // Two orders that can both be filled at the current price
+        for _ in 0..2 {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::PlaceLimitOrder {
+                    offer_asset: AssetUnchecked::new(
+                        AssetInfoUnchecked::Native(UION.to_string()),
+                        offer_amount,
+                    ),
+                    ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                    minimum_receive: simulated,
+                    tip: Some(tip),
+                },
+                &[Coin::new((offer_amount + tip).u128(), UION)],
+                owner,
+            )
+            .unwrap();
+        }
+
+        // The first fill moves the price, so that the second order no longer
+        // returns its minimum receive. It is skipped without reverting the first.
+        let uatom_before = bank_balance_query(&app, owner.address(), UATOM.to_string()).unwrap();
+        let uion_before = bank_balance_query(&app, keeper.address(), UION.to_string()).unwrap();
+        let res = wasm
+            .execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteLimitOrders {
+                    order_ids: vec![0, 1],
+                },
+                &[],
+                keeper,
+            )
+            .unwrap();
+        let uatom_after = bank_balance_query(&app, owner.address(), UATOM.to_string()).unwrap();
+        let uion_after = bank_balance_query(&app, keeper.address(), UION.to_string()).unwrap();
+        assert_eq!(uatom_after - uatom_before, simulated);
+        assert_eq!(uion_after - uion_before, tip);
+        assert!(res.events.iter().any(|event| event.ty
+            == "wasm-apollo/cw-dex-router/limit_order_fill_failed"
+            && event
+                .attributes
+                .iter()
+                .any(|attr| attr.key == "order_id" && attr.value == "1")));
+
+        // The second order is left in place with its escrow
+        let orders: Vec<LimitOrder> = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::LimitOrdersByOwner {
+                    owner: owner.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(orders.iter().map(|x| x.id).collect::<Vec<_>>(), vec![1]);
+        let router_uion =
+            bank_balance_query(&app, cw_dex_router_addr.clone(), UION.to_string()).unwrap();
+        assert_eq!(router_uion, offer_amount + tip);
+    }
+
+    #[test]
+    fn test_limit_orders_can_not_be_placed_during_swap() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let owner = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+        let second_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetPath {
+                offer_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                path: osmosis_swap_operations_list_from_vec(UION_UATOM_PATH).into(),
+                bidirectional: false,
+                append: false,
+                reference_amount: None,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let offer_amount = Uint128::new(10_000);
+        let place_limit_order = ExecuteMsg::PlaceLimitOrder {
+            offer_asset: AssetUnchecked::new(
+                AssetInfoUnchecked::Native(UION.to_string()),
+                offer_amount,
+            ),
+            ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+            minimum_receive: Uint128::one(),
+            tip: None,
+        };
+        let swap = |post_swap_action: Option<PostSwapAction>| {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteSwapOperations {
+                    operations: osmosis_swap_operations_list_from_vec(&[(2, UATOM, UION)]).into(),
+                    offer_amount: None,
+                    minimum_receive: None,
+                    max_slippage: None,
+                    to: None,
+                    deadline: None,
+                    post_swap_action,
+                    recipients: None,
+                    referral: None,
+                },
+                &[Coin::new(offer_amount.u128(), UATOM)],
+                owner,
+            )
+        };
+
+        // The router is called back from within the swap by the second router,
+        // while the callbacks measuring the output of the swap are still to be
+        // executed
+        let err = swap(Some(PostSwapAction {
+            contract_addr: second_router_addr,
+            msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperations {
+                operations: osmosis_swap_operations_list_from_vec(UION_UATOM_PATH).into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: Some(PostSwapAction {
+                    contract_addr: cw_dex_router_addr.clone(),
+                    msg: to_json_binary(&place_limit_order).unwrap(),
+                }),
+                recipients: None,
+                referral: None,
+            })
+            .unwrap(),
+        }))
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can not be moved while a swap is executed"));
+
+        // Once a swap is done orders can be placed again
+        swap(None).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &place_limit_order,
+            &[Coin::new(offer_amount.u128(), UION)],
+            owner,
+        )
+        .unwrap();
+    }
+    #[test]
+    fn test_dca_schedule() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let owner = &accs[1];
+        let keeper = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetPath {
+                offer_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                path: osmosis_swap_operations_list_from_vec(UION_UATOM_PATH).into(),
+                bidirectional: false,
+                append: false,
+                reference_amount: None,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        // Budget for two full slices and one partial slice
+        let interval = 60;
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::CreateDcaSchedule {
+                offer: AssetInfoUnchecked::Native(UION.to_string()),
+                ask: AssetInfoUnchecked::Native(UATOM.to_string()),
+                amount_per_interval: Uint128::new(10_000),
+                interval,
+                total_budget: Uint128::new(25_000),
+                max_slippage: Some(Decimal::percent(5)),
+            },
+            &[Coin::new(25_000, UION)],
+            owner,
+        )
+        .unwrap();
+        let trigger = || {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::TriggerDca { schedule_id: 0 },
+                &[],
+                keeper,
+            )
+        };
+        let query_schedule = || -> DcaSchedule {
+            wasm.query(
+                &cw_dex_router_addr,
+                &QueryMsg::DcaSchedule { schedule_id: 0 },
+            )
+            .unwrap()
+        };
+
+        // The first slice can be swapped right away, the next one only after the
+        // interval
+        let uatom_before = bank_balance_query(&app, owner.address(), UATOM.to_string()).unwrap();
+        trigger().unwrap();
+        let uatom_after = bank_balance_query(&app, owner.address(), UATOM.to_string()).unwrap();
+        assert!(uatom_after > uatom_before);
+        assert_eq!(query_schedule().remaining, Uint128::new(15_000));
+        let err = trigger().unwrap_err();
+        assert!(err.to_string().contains("can not be swapped before"));
+
+        // A paused schedule can not be triggered
+        app.increase_time(interval);
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetDcaSchedulePaused {
+                schedule_id: 0,
+                paused: true,
+            },
+            &[],
+            owner,
+        )
+        .unwrap();
+        let err = trigger().unwrap_err();
+        assert!(err.to_string().contains("is paused"));
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetDcaSchedulePaused {
+                schedule_id: 0,
+                paused: false,
+            },
+            &[],
+            owner,
+        )
+        .unwrap();
+        trigger().unwrap();
+        assert_eq!(query_schedule().remaining, Uint128::new(5_000));
+
+        // Withdrawing refunds the rest of the budget and removes the schedule
+        let uion_before = bank_balance_query(&app, owner.address(), UION.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::WithdrawDcaSchedule { schedule_id: 0 },
+            &[],
+            owner,
+        )
+        .unwrap();
+        let uion_after = bank_balance_query(&app, owner.address(), UION.to_string()).unwrap();
+        assert_eq!(uion_after - uion_before, Uint128::new(5_000));
+        let schedules: Vec<DcaSchedule> = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::DcaSchedulesByOwner {
+                    owner: owner.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert!(schedules.is_empty());
+    }
+
+    #[test]
+    fn test_dca_schedules_can_not_be_changed_during_swap() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let owner = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+        let second_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::SetPath {
+                offer_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                ask_asset: AssetInfoUnchecked::Native(UATOM.to_string()),
+                path: osmosis_swap_operations_list_from_vec(UION_UATOM_PATH).into(),
+                bidirectional: false,
+                append: false,
+                reference_amount: None,
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::CreateDcaSchedule {
+                offer: AssetInfoUnchecked::Native(UION.to_string()),
+                ask: AssetInfoUnchecked::Native(UATOM.to_string()),
+                amount_per_interval: Uint128::new(10_000),
+                interval: 60,
+                total_budget: Uint128::new(20_000),
+                max_slippage: None,
+            },
+            &[Coin::new(20_000, UION)],
+            owner,
+        )
+        .unwrap();
+
+        // The router is called back from within a swap by the second router,
+        // while the callbacks measuring the output of the swap are still to be
+        // executed
+        let actions = [
+            ExecuteMsg::CreateDcaSchedule {
+                offer: AssetInfoUnchecked::Native(UION.to_string()),
+                ask: AssetInfoUnchecked::Native(UATOM.to_string()),
+                amount_per_interval: Uint128::new(1_000),
+                interval: 60,
+                total_budget: Uint128::new(1_000),
+                max_slippage: None,
+            },
+            ExecuteMsg::TriggerDca { schedule_id: 0 },
+            ExecuteMsg::WithdrawDcaSchedule { schedule_id: 0 },
+        ];
+        for action in actions {
+            let err = wasm
+                .execute(
+                    &cw_dex_router_addr,
+                    &ExecuteMsg::ExecuteSwapOperations {
+                        operations: osmosis_swap_operations_list_from_vec(&[(2, UATOM, UION)])
+                            .into(),
+                        offer_amount: None,
+                        minimum_receive: None,
+                        max_slippage: None,
+                        to: None,
+                        deadline: None,
+                        post_swap_action: Some(PostSwapAction {
+                            contract_addr: second_router_addr.clone(),
+                            msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperations {
+                                operations: osmosis_swap_operations_list_from_vec(UION_UATOM_PATH)
+                                    .into(),
+                                offer_amount: None,
+                                minimum_receive: None,
+                                max_slippage: None,
+                                to: None,
+                                deadline: None,
+                                post_swap_action: Some(PostSwapAction {
+                                    contract_addr: cw_dex_router_addr.clone(),
+                                    msg: to_json_binary(&action).unwrap(),
+                                }),
+                                recipients: None,
+                                referral: None,
+                            })
+                            .unwrap(),
+                        }),
+                        recipients: None,
+                        referral: None,
+                    },
+                    &[Coin::new(10_000, UATOM)],
+                    owner,
+                )
+                .unwrap_err();
+            assert!(err
+                .to_string()
+                .contains("can not be moved while a swap is executed"));
+        }
+
+        // Outside of a swap the schedule can be triggered
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::TriggerDca { schedule_id: 0 },
+            &[],
+            owner,
+        )
+        .unwrap();
+    }
+    #[test_case(false ; "charged")]
+    #[test_case(true ; "exempt")]
+    fn test_execute_swap_operations_with_protocol_fee(exempt: bool) {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let fee_collector = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::UpdateFeeConfig {
+                fee_bps: 100,
+                fee_collector: fee_collector.address(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+        if exempt {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::UpdateFeeExemptCallers {
+                    add: vec![sender.address()],
+                    remove: vec![],
+                },
+                &[],
+                admin,
+            )
+            .unwrap();
+        }
+
+        let operations = osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]);
+        let offer_amount = Uint128::new(10_000);
+        let simulated: SimulateSwapResponse = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            simulated.fee_amount,
+            simulated.gross_amount.multiply_ratio(100u128, 10_000u128)
+        );
+        assert_eq!(
+            simulated.net_amount,
+            simulated.gross_amount - simulated.fee_amount
+        );
+
+        // The minimum receive applies to the output after the fee, unless the
+        // sender is exempt
+        let (expected_received, expected_fee) = if exempt {
+            (simulated.gross_amount, Uint128::zero())
+        } else {
+            (simulated.net_amount, simulated.fee_amount)
+        };
+        let sender_before = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+        let collector_before =
+            bank_balance_query(&app, fee_collector.address(), UATOM.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: operations.into(),
+                offer_amount: None,
+                minimum_receive: Some(expected_received),
+                max_slippage: None,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let sender_after = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+        let collector_after =
+            bank_balance_query(&app, fee_collector.address(), UATOM.to_string()).unwrap();
+        assert_eq!(sender_after - sender_before, expected_received);
+        assert_eq!(collector_after - collector_before, expected_fee);
+    }
+
+    #[test]
+    fn test_protocol_fee_on_split_route_exact_out_and_batch_swap() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let fee_collector = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::UpdateFeeConfig {
+                fee_bps: 100,
+                fee_collector: fee_collector.address(),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let operations = osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]);
+        let offer_amount = Uint128::new(10_000);
+        let balances = || {
+            (
+                bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap(),
+                bank_balance_query(&app, fee_collector.address(), UATOM.to_string()).unwrap(),
+            )
+        };
+        let simulate = || -> SimulateSwapResponse {
+            wasm.query(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap()
+        };
+
+        // Split route with a single leg
+        let route = SplitRouteUnchecked::new(vec![SplitRouteLegUnchecked {
+            operations: operations.clone().into(),
+            weight: Decimal::one(),
+        }]);
+        let simulated: SimulateSwapResponse = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSplitRoute {
+                    offer_amount,
+                    route: route.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(simulated, simulate());
+        let (sender_before, collector_before) = balances();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSplitRoute {
+                route,
+                offer_amount,
+                minimum_receive: Some(simulated.net_amount),
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let (sender_after, collector_after) = balances();
+        assert_eq!(sender_after - sender_before, simulated.net_amount);
+        assert_eq!(collector_after - collector_before, simulated.fee_amount);
+
+        // Batch swap with a single entry
+        let simulated = simulate();
+        let (sender_before, collector_before) = balances();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::BatchSwap {
+                swaps: vec![BatchSwapEntry {
+                    operations: operations.clone().into(),
+                    offer_amount,
+                    minimum_receive: Some(simulated.net_amount),
+                    to: None,
+                }],
+                failure_policy: BatchFailurePolicy::AllOrNothing,
+                deadline: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let (sender_after, collector_after) = balances();
+        assert_eq!(sender_after - sender_before, simulated.net_amount);
+        assert_eq!(collector_after - collector_before, simulated.fee_amount);
+
+        // Exact out swaps enough to receive the ask amount after the fee
+        let ask_amount = Uint128::new(1_000);
+        let needed_offer: Uint128 = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateReverseSwapOperations {
+                    ask_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap();
+        let (sender_before, collector_before) = balances();
+        let offer_before = bank_balance_query(&app, sender.address(), UION.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperationsExactOut {
+                operations: operations.into(),
+                ask_amount,
+                max_offer: offer_amount,
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+            },
+            &[Coin::new(offer_amount.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let (sender_after, collector_after) = balances();
+        let offer_after = bank_balance_query(&app, sender.address(), UION.to_string()).unwrap();
+        assert_eq!(offer_before - offer_after, needed_offer);
+        assert!(sender_after - sender_before >= ask_amount);
+        assert!(collector_after > collector_before);
+    }
+
+    #[test]
+    fn test_execute_swap_operations_with_referral() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let referrer = &accs[2];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::UpdateConfig {
+                hub_assets: None,
+                max_referral_fee_bps: Some(50),
+            },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let operations = osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]);
+        let offer_amount = Uint128::new(10_000);
+        let swap = |fee_bps: u16| {
+            wasm.execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteSwapOperations {
+                    operations: operations.clone().into(),
+                    offer_amount: None,
+                    minimum_receive: None,
+                    max_slippage: None,
+                    to: None,
+                    deadline: None,
+                    post_swap_action: None,
+                    recipients: None,
+                    referral: Some(Referral {
+                        address: referrer.address(),
+                        fee_bps,
+                    }),
+                },
+                &[Coin::new(offer_amount.u128(), UION)],
+                sender,
+            )
+        };
+
+        // The referral fee is capped by the config
+        let err = swap(100).unwrap_err();
+        assert!(err.to_string().contains("above the maximum of 50"));
+
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap()
+            .gross_amount;
+        let sender_before = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+        let referrer_before =
+            bank_balance_query(&app, referrer.address(), UATOM.to_string()).unwrap();
+        swap(50).unwrap();
+        let sender_after = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+        let referrer_after =
+            bank_balance_query(&app, referrer.address(), UATOM.to_string()).unwrap();
+        let referral_fee = simulated.multiply_ratio(50u128, 10_000u128);
+        assert_eq!(referrer_after - referrer_before, referral_fee);
+        assert_eq!(sender_after - sender_before, simulated - referral_fee);
+
+        // The whole output counts towards the volume of the referrer
+        let volumes: Vec<Asset> = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::ReferralVolumes {
+                    referrer: referrer.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            volumes,
+            vec![Asset::new(AssetInfo::Native(UATOM.to_string()), simulated)]
+        );
+    }
+
+    #[test_case(false, -1, false ; "block height passed")]
+    #[test_case(false, 100, true ; "block height not passed")]
+    #[test_case(true, -1, false ; "timestamp passed")]
+    #[test_case(true, 100, true ; "timestamp not passed")]
+    fn test_execute_swap_operations_with_deadline(timestamp: bool, offset: i64, succeeds: bool) {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // The deadline is offset from the current block in blocks or seconds
+        let deadline = if timestamp {
+            let now = app.get_block_timestamp();
+            Deadline::Timestamp(if offset < 0 {
+                now.minus_seconds(offset.unsigned_abs())
+            } else {
+                now.plus_seconds(offset as u64)
+            })
+        } else {
+            Deadline::BlockHeight((app.get_block_height() + offset) as u64)
+        };
+
+        let res = wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperations {
+                operations: osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM)]).into(),
+                offer_amount: None,
+                minimum_receive: None,
+                max_slippage: None,
+                to: None,
+                deadline: Some(deadline),
+                post_swap_action: None,
+                recipients: None,
+                referral: None,
+            },
+            &[Coin::new(10_000, UION)],
+            sender,
+        );
+        assert_eq!(res.is_ok(), succeeds);
+        if !succeeds {
+            assert!(res.unwrap_err().to_string().contains("Deadline"));
+        }
+    }
+
+    #[test]
+    fn test_optimize_and_execute_split_route() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // Pools 1 and 3 are both uosmo/uatom pools with equal liquidity
+        let path_through_pool_1 = osmosis_swap_operations_list_from_vec(&[(1, UOSMO, UATOM)]);
+        let path_through_pool_3 = osmosis_swap_operations_list_from_vec(&[(3, UOSMO, UATOM)]);
+        let offer_amount = Uint128::new(100_000);
+
+        let split: SplitRouteResponse = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::OptimizeSplitRoute {
+                    offer_amount,
+                    paths: vec![
+                        path_through_pool_1.clone().into(),
+                        path_through_pool_3.into(),
+                    ],
+                    steps: Some(10),
+                },
+            )
+            .unwrap();
+
+        // The amount should be split evenly and return more than a single path
+        assert_eq!(split.route.legs().len(), 2);
+        for leg in split.route.legs() {
+            assert_eq!(leg.weight, Decimal::percent(50));
+        }
+        let single_path_output = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: path_through_pool_1.into(),
+                },
+            )
+            .unwrap()
+            .gross_amount;
+        assert!(split.return_amount > single_path_output);
+
+        // Coins besides the offer asset are not accepted
+        let err = wasm
+            .execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteSplitRoute {
+                    route: split.route.clone().into(),
+                    offer_amount,
+                    minimum_receive: None,
+                    to: None,
+                    deadline: None,
+                    post_swap_action: None,
+                },
+                &[
+                    Coin::new(offer_amount.u128(), UOSMO),
+                    Coin::new(1_000, UION),
+                ],
+                sender,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Incorrect amount of native token sent"));
+
+        // Execute the split and check that the combined output is received
+        let balance_before = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSplitRoute {
+                route: split.route.into(),
+                offer_amount,
+                minimum_receive: Some(split.return_amount),
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+            },
+            &[Coin::new(offer_amount.u128(), UOSMO)],
+            sender,
+        )
+        .unwrap();
+        let balance_after = bank_balance_query(&app, sender.address(), UATOM.to_string()).unwrap();
+        assert_eq!(balance_after - balance_before, split.return_amount);
+    }
+
+    #[test]
+    fn test_execute_swap_operations_exact_out() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let sender = &accs[1];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        let recipient = &accs[2];
+        let operations =
+            osmosis_swap_operations_list_from_vec(&[(2, UION, UATOM), (1, UATOM, UOSMO)]);
+        let ask_amount = Uint128::new(1000);
+
+        let offer_amount: Uint128 = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateReverseSwapOperations {
+                    ask_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap();
+
+        // The offer amount should be the smallest amount that returns the ask amount
+        let simulate = |offer_amount: Uint128| -> Uint128 {
+            wasm.query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: operations.clone().into(),
+                },
+            )
+            .unwrap()
+            .gross_amount
+        };
+        assert!(simulate(offer_amount) >= ask_amount);
+        assert!(simulate(offer_amount - Uint128::one()) < ask_amount);
+
+        // Execute with a larger max offer and check that the rest is refunded
+        let max_offer = offer_amount + Uint128::new(500);
+        let uion_before = bank_balance_query(&app, sender.address(), UION.to_string()).unwrap();
+        let uosmo_before =
+            bank_balance_query(&app, recipient.address(), UOSMO.to_string()).unwrap();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperationsExactOut {
+                operations: operations.clone().into(),
+                ask_amount,
+                max_offer,
+                to: Some(recipient.address()),
+                deadline: None,
+                post_swap_action: None,
+            },
+            &[Coin::new(max_offer.u128(), UION)],
+            sender,
+        )
+        .unwrap();
+        let uion_after = bank_balance_query(&app, sender.address(), UION.to_string()).unwrap();
+        let uosmo_after = bank_balance_query(&app, recipient.address(), UOSMO.to_string()).unwrap();
+
+        assert_eq!(uion_before - uion_after, offer_amount);
+        assert!(uosmo_after - uosmo_before >= ask_amount);
+
+        // Coins besides the max offer are not accepted
+        let err = wasm
+            .execute(
+                &cw_dex_router_addr,
+                &ExecuteMsg::ExecuteSwapOperationsExactOut {
+                    operations: operations.clone().into(),
+                    ask_amount,
+                    max_offer,
+                    to: None,
+                    deadline: None,
+                    post_swap_action: None,
+                },
+                &[Coin::new(max_offer.u128(), UION), Coin::new(1_000, UOSMO)],
+                sender,
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Incorrect amount of native token sent"));
+
+        // A max offer below the needed amount should fail
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::ExecuteSwapOperationsExactOut {
+                operations: operations.into(),
+                ask_amount,
+                max_offer: offer_amount - Uint128::one(),
+                to: None,
+                deadline: None,
+                post_swap_action: None,
+            },
+            &[Coin::new(offer_amount.u128() - 1, UION)],
+            sender,
+        )
+        .unwrap_err();
+    }
+
+    #[test_case(UOSMO, UATOM, None, 1 ; "uosmo/uatom direct route")]
+    #[test_case(UOSMO, UION, None, 2 ; "uosmo/uion two hops route")]
+    #[test_case(UOSMO, UION, Some(1), 0 => matches Err(_) ; "uosmo/uion max hops too low")]
+    fn test_query_best_route(
+        offer_asset: &str,
+        ask_asset: &str,
+        max_hops: Option<u32>,
+        expected_hops: usize,
+    ) -> StdResult<()> {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        // Register all pools created in setup
+        let pools = (1..=4)
+            .map(|pool_id| Pool::Osmosis(OsmosisPool::unchecked(pool_id)))
+            .collect::<Vec<_>>();
+        wasm.execute(
+            &cw_dex_router_addr,
+            &ExecuteMsg::RegisterPools { pools },
+            &[],
+            admin,
+        )
+        .unwrap();
+
+        let offer_amount = Uint128::from(1000u128);
+        let best_route: BestRouteResponse = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::BestRoute {
+                    offer_asset: AssetUnchecked::new(
+                        AssetInfoUnchecked::Native(offer_asset.to_string()),
+                        offer_amount,
+                    ),
+                    ask_asset: AssetInfoUnchecked::Native(ask_asset.to_string()),
+                    max_hops,
+                },
+            )
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+        let operations: Vec<SwapOperation> = best_route.operations.clone().into();
+        assert_eq!(operations.len(), expected_hops);
+
+        // The returned amount should match simulating the route
+        let simulated = wasm
+            .query::<_, SimulateSwapResponse>(
+                &cw_dex_router_addr,
+                &QueryMsg::SimulateSwapOperations {
+                    offer_amount,
+                    operations: best_route.operations.into(),
+                },
+            )
+            .unwrap()
+            .gross_amount;
+        assert_eq!(simulated, best_route.return_amount);
+
+        Ok(())
+    }
+
     // Tests disabled due to breaking changes in cw-it
     // #[test_case(&[((UOSMO, UATOM), UOSMO_UATOM_PATH)], &[(UOSMO,
     // Uint128::from(1000u128))],         UATOM, None, None  ; "uosmo/uatom
@@ -556,11 +2735,19 @@ mod osmosis_tests {
         let wasm = Wasm::new(&app);
         let supported_offer_assets: Vec<AssetInfo> = wasm.query(
             &cw_dex_router_addr,
-            &QueryMsg::SupportedOfferAssets { ask_asset },
+            &QueryMsg::SupportedOfferAssets {
+                ask_asset,
+                start_after: None,
+                limit: None,
+            },
         )?;
         let supported_ask_assets: Vec<AssetInfo> = wasm.query(
             &cw_dex_router_addr,
-            &QueryMsg::SupportedAskAssets { offer_asset },
+            &QueryMsg::SupportedAskAssets {
+                offer_asset,
+                start_after: None,
+                limit: None,
+            },
         )?;
 
         println!("expected_offer_assets: {:?}", expected_offer_assets);
@@ -574,4 +2761,249 @@ mod osmosis_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_supported_ask_assets_pagination() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        set_paths(
+            &app,
+            &cw_dex_router_addr,
+            &[
+                ((UOSMO, UATOM), UOSMO_UATOM_PATH),
+                ((UOSMO, UION), UOSMO_UATOM_UION_PATH),
+            ],
+            admin,
+            false,
+        )
+        .unwrap();
+
+        let query_page = |start_after: Option<&str>| -> Vec<AssetInfo> {
+            wasm.query(
+                &cw_dex_router_addr,
+                &QueryMsg::SupportedAskAssets {
+                    offer_asset: AssetInfoUnchecked::Native(UOSMO.to_string()),
+                    start_after: start_after.map(|x| AssetInfoUnchecked::Native(x.to_string())),
+                    limit: Some(1),
+                },
+            )
+            .unwrap()
+        };
+
+        assert_eq!(query_page(None), vec![AssetInfo::Native(UATOM.to_string())]);
+        assert_eq!(
+            query_page(Some(UATOM)),
+            vec![AssetInfo::Native(UION.to_string())]
+        );
+        assert_eq!(query_page(Some(UION)), vec![]);
+
+        // The offer assets for uion come from the ask asset index
+        let offer_assets: Vec<AssetInfo> = wasm
+            .query(
+                &cw_dex_router_addr,
+                &QueryMsg::SupportedOfferAssets {
+                    ask_asset: AssetInfoUnchecked::Native(UION.to_string()),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(offer_assets, vec![AssetInfo::Native(UOSMO.to_string())]);
+    }
+
+    #[test]
+    fn test_query_all_paths() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        set_paths(
+            &app,
+            &cw_dex_router_addr,
+            &[
+                ((UOSMO, UATOM), UOSMO_UATOM_PATH),
+                ((UOSMO, UION), UOSMO_UATOM_UION_PATH),
+            ],
+            admin,
+            true,
+        )
+        .unwrap();
+
+        let query_all_paths = |start_after: Option<(&str, &str)>,
+                               limit: Option<u32>,
+                               filter: Option<PathFilter>|
+         -> AllPathsResponse {
+            wasm.query(
+                &cw_dex_router_addr,
+                &QueryMsg::AllPaths {
+                    start_after: start_after.map(|(offer, ask)| {
+                        (
+                            AssetInfoUnchecked::Native(offer.to_string()),
+                            AssetInfoUnchecked::Native(ask.to_string()),
+                        )
+                    }),
+                    limit,
+                    filter,
+                },
+            )
+            .unwrap()
+        };
+        let pairs = |response: AllPathsResponse| -> Vec<(AssetInfo, AssetInfo)> {
+            response
+                .paths
+                .into_iter()
+                .map(|entry| (entry.offer_asset, entry.ask_asset))
+                .collect()
+        };
+        let pair = |offer: &str, ask: &str| {
+            (
+                AssetInfo::Native(offer.to_string()),
+                AssetInfo::Native(ask.to_string()),
+            )
+        };
+
+        // All four directions are stored
+        let entries = query_all_paths(None, None, None).paths;
+        assert_eq!(entries.len(), 4);
+        assert!(entries.iter().all(|entry| !entry.disabled));
+
+        // Paginate one pair at a time, continuing after the returned pair until
+        // all pairs are read
+        let page = query_all_paths(None, Some(1), None);
+        assert_eq!(page.next_start_after, Some(pair(UATOM, UOSMO)));
+        assert_eq!(pairs(page), vec![pair(UATOM, UOSMO)]);
+        let page = query_all_paths(Some((UATOM, UOSMO)), Some(1), None);
+        assert_eq!(page.next_start_after, Some(pair(UION, UOSMO)));
+        assert_eq!(pairs(page), vec![pair(UION, UOSMO)]);
+        let page = query_all_paths(Some((UOSMO, UATOM)), Some(1), None);
+        assert_eq!(page.next_start_after, None);
+        assert_eq!(pairs(page), vec![pair(UOSMO, UION)]);
+
+        // Filter by offer asset
+        let filter = PathFilter {
+            offer_asset: Some(AssetInfoUnchecked::Native(UOSMO.to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            pairs(query_all_paths(None, None, Some(filter.clone()))),
+            vec![pair(UOSMO, UATOM), pair(UOSMO, UION)]
+        );
+        assert_eq!(
+            pairs(query_all_paths(Some((UOSMO, UATOM)), Some(1), Some(filter))),
+            vec![pair(UOSMO, UION)]
+        );
+
+        // Filter by ask asset
+        let filter = PathFilter {
+            ask_asset: Some(AssetInfoUnchecked::Native(UOSMO.to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            pairs(query_all_paths(None, None, Some(filter.clone()))),
+            vec![pair(UATOM, UOSMO), pair(UION, UOSMO)]
+        );
+        assert_eq!(
+            pairs(query_all_paths(Some((UATOM, UOSMO)), Some(1), Some(filter))),
+            vec![pair(UION, UOSMO)]
+        );
+
+        // Filter by both assets
+        let filter = PathFilter {
+            offer_asset: Some(AssetInfoUnchecked::Native(UION.to_string())),
+            ask_asset: Some(AssetInfoUnchecked::Native(UOSMO.to_string())),
+            ..Default::default()
+        };
+        assert_eq!(
+            pairs(query_all_paths(None, None, Some(filter.clone()))),
+            vec![pair(UION, UOSMO)]
+        );
+        assert_eq!(
+            pairs(query_all_paths(Some((UION, UOSMO)), None, Some(filter))),
+            vec![]
+        );
+
+        // Filter by hop count and pool
+        let filter = PathFilter {
+            hops: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            pairs(query_all_paths(None, None, Some(filter))),
+            vec![pair(UION, UOSMO), pair(UOSMO, UION)]
+        );
+        let filter = PathFilter {
+            ask_asset: Some(AssetInfoUnchecked::Native(UOSMO.to_string())),
+            pool: Some(Pool::Osmosis(OsmosisPool::unchecked(2))),
+            ..Default::default()
+        };
+        let entries = query_all_paths(None, None, Some(filter)).paths;
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.offer_asset.clone(), entry.ask_asset.clone()))
+                .collect::<Vec<_>>(),
+            vec![pair(UION, UOSMO)]
+        );
+        assert_eq!(
+            entries[0].path,
+            osmosis_swap_operations_list_from_vec(UOSMO_UATOM_UION_PATH).reverse()
+        );
+    }
+
+    #[test]
+    fn test_query_path_health() {
+        let (app, accs, code_id) = setup();
+        let wasm = Wasm::new(&app);
+        let admin = &accs[0];
+        let cw_dex_router_addr = instantiate_cw_dex_router(&app, admin, code_id).unwrap();
+
+        set_paths(
+            &app,
+            &cw_dex_router_addr,
+            &[((UOSMO, UION), UOSMO_UATOM_UION_PATH)],
+            admin,
+            false,
+        )
+        .unwrap();
+
+        let query_path_health = |reference_amount: u128| -> Vec<PathHealth> {
+            let response: PathHealthResponse = wasm
+                .query(
+                    &cw_dex_router_addr,
+                    &QueryMsg::PathHealth {
+                        reference_amount: Uint128::new(reference_amount),
+                        start_after: None,
+                        limit: None,
+                        filter: None,
+                    },
+                )
+                .unwrap();
+            assert_eq!(response.next_start_after, None);
+            response.paths
+        };
+
+        // Every hop is simulated and the larger amount has more price impact
+        let small = query_path_health(10_000).remove(0);
+        let large = query_path_health(200_000).remove(0);
+        for health in [&small, &large] {
+            assert_eq!(health.hops.len(), 2);
+            assert!(health.hops.iter().all(|hop| hop.error.is_none()));
+            assert_eq!(
+                health.return_amount,
+                health.hops.last().unwrap().return_amount
+            );
+        }
+        assert!(large.hops[0].price_impact.unwrap() > small.hops[0].price_impact.unwrap());
+
+        // An amount larger than the pool liquidity fails on the first hop
+        // without failing the query
+        let health = query_path_health(1_000_000_000_000).remove(0);
+        assert_eq!(health.hops.len(), 1);
+        assert!(health.hops[0].error.is_some());
+        assert_eq!(health.return_amount, None);
+    }
 }